@@ -0,0 +1,30 @@
+// ホーム画面へのインストールとオフライン起動のための設定
+(function () {
+  // pwa.js はアプリと同じディレクトリに置くため、公開するパスは pwa.js の URL から求める
+  var basePath = new URL("./", document.currentScript.src).pathname;
+
+  var manifest = document.createElement("link");
+  manifest.rel = "manifest";
+  manifest.href = basePath + "manifest.webmanifest";
+  document.head.appendChild(manifest);
+
+  var themeColor = document.createElement("meta");
+  themeColor.name = "theme-color";
+  themeColor.content = "#00d1b2";
+  document.head.appendChild(themeColor);
+
+  var appleTouchIcon = document.createElement("link");
+  appleTouchIcon.rel = "apple-touch-icon";
+  appleTouchIcon.href = basePath + "icons/icon-192.png";
+  document.head.appendChild(appleTouchIcon);
+
+  if ("serviceWorker" in navigator) {
+    window.addEventListener("load", function () {
+      navigator.serviceWorker
+        .register(basePath + "sw.js", { scope: basePath })
+        .catch(function (err) {
+          console.warn("Failed to register service worker", err);
+        });
+    });
+  }
+})();
@@ -0,0 +1,144 @@
+// アンシンめし Service Worker
+//
+// - アプリ本体 (HTML / wasm / CSS / フォント / アイコン) はインストール時にキャッシュし、
+//   キャッシュを優先して返しつつ裏で更新する
+// - データ (Google Apps Script とそのスナップショットの data.json / data.bin) はネットワークを優先し、失敗した場合は最後に取得したものを返す
+//
+// __APP_VERSION__ は deploy.sh で .env の APP_VERSION に置き換える。
+// sw.js はアプリと同じディレクトリに置くため、公開するパスは sw.js の URL から求める。
+
+var BASE_PATH = new URL("./", self.location).pathname;
+var VERSION = "__APP_VERSION__";
+var APP_CACHE = "anshin-meshi-app-" + VERSION;
+var DATA_CACHE = "anshin-meshi-data";
+
+var APP_FILES = [
+  "",
+  "index.html",
+  "assets/dioxus/anshin-meshi.js",
+  "assets/dioxus/anshin-meshi_bg.wasm",
+  "styles/bulma.min.css",
+  "styles/main.css",
+  "fonts/27_ninaroman-Regular.otf",
+  "header.svg",
+  "favicon.ico",
+  "pwa.js",
+  "manifest.webmanifest",
+  "icons/icon-192.png",
+  "icons/icon-512.png",
+  "icons/icon.svg",
+].map(function (path) {
+  return BASE_PATH + path;
+});
+
+var DATA_HOSTS = ["script.google.com", "script.googleusercontent.com"];
+var DATA_SNAPSHOTS = [BASE_PATH + "data.json", BASE_PATH + "data.bin"];
+
+self.addEventListener("install", function (event) {
+  event.waitUntil(
+    caches
+      .open(APP_CACHE)
+      .then(function (cache) {
+        // 1 ファイルの取得に失敗してもインストールは続ける
+        return Promise.all(
+          APP_FILES.map(function (file) {
+            return cache.add(file).catch(function (err) {
+              console.warn("Failed to cache " + file, err);
+            });
+          })
+        );
+      })
+      .then(function () {
+        return self.skipWaiting();
+      })
+  );
+});
+
+self.addEventListener("activate", function (event) {
+  event.waitUntil(
+    caches
+      .keys()
+      .then(function (keys) {
+        return Promise.all(
+          keys
+            .filter(function (key) {
+              return key !== APP_CACHE && key !== DATA_CACHE;
+            })
+            .map(function (key) {
+              return caches.delete(key);
+            })
+        );
+      })
+      .then(function () {
+        return self.clients.claim();
+      })
+  );
+});
+
+self.addEventListener("fetch", function (event) {
+  var request = event.request;
+  if (request.method !== "GET") {
+    return;
+  }
+  var url = new URL(request.url);
+
+  if (DATA_HOSTS.indexOf(url.hostname) !== -1) {
+    event.respondWith(networkFirst(request, DATA_CACHE));
+    return;
+  }
+  if (url.origin === self.location.origin && DATA_SNAPSHOTS.indexOf(url.pathname) !== -1) {
+    event.respondWith(networkFirst(request, DATA_CACHE));
+    return;
+  }
+  if (url.origin !== self.location.origin || url.pathname.indexOf(BASE_PATH) !== 0) {
+    return;
+  }
+  if (request.mode === "navigate") {
+    // /product/... などのパスもアプリ本体の index.html で表示する
+    event.respondWith(
+      fetch(request).catch(function () {
+        return caches.match(BASE_PATH + "index.html");
+      })
+    );
+    return;
+  }
+  event.respondWith(staleWhileRevalidate(request, APP_CACHE));
+});
+
+function networkFirst(request, cacheName) {
+  return caches.open(cacheName).then(function (cache) {
+    return fetch(request)
+      .then(function (response) {
+        if (response.ok) {
+          cache.put(request, response.clone());
+        }
+        return response;
+      })
+      .catch(function (err) {
+        return cache.match(request).then(function (cached) {
+          if (cached) {
+            return cached;
+          }
+          throw err;
+        });
+      });
+  });
+}
+
+function staleWhileRevalidate(request, cacheName) {
+  return caches.open(cacheName).then(function (cache) {
+    return cache.match(request).then(function (cached) {
+      var fetched = fetch(request).then(function (response) {
+        if (response.ok) {
+          cache.put(request, response.clone());
+        }
+        return response;
+      });
+      if (cached) {
+        fetched.catch(function () {});
+        return cached;
+      }
+      return fetched;
+    });
+  });
+}
@@ -28,6 +28,17 @@ else
     exit 1
 fi
 
+# Dioxus.toml の公開パスが .env の APP_BASE_PATH と揃っているか確かめる
+# (sw.js / pwa.js / manifest.webmanifest は自分の URL から公開パスを求める)
+base_path=$(grep "^APP_BASE_PATH=" .env | sed 's/APP_BASE_PATH=//' | sed 's:/*$::')
+base_name=${base_path#/}
+if ! grep -q "^base_path = \"$base_name\"" Dioxus.toml \
+    || grep -E '^(style|script) = ' Dioxus.toml | grep -o '"/[^"]*"' | grep -qv "^\"$base_path/"; then
+    echo "エラー: Dioxus.toml の base_path / style / script を APP_BASE_PATH ($base_path) に揃えてください。"
+    echo "Failed build." `date '+%y/%m/%d %H:%M:%S'`
+    exit 1
+fi
+
 # Build
 echo "Start build." `date '+%y/%m/%d %H:%M:%S'`
 dx build --release
@@ -38,6 +49,27 @@ else
     exit 1
 fi
 
+# Service Worker のキャッシュ名にバージョンを埋め込む
+sed -i "s/__APP_VERSION__/$app_version/" dist/sw.js
+
+# GitHub Pages で /product/... などのパスを直接開いたときもアプリを表示する
+cp dist/index.html dist/404.html
+
+# Google Apps Script が使えないときの代替として、データのスナップショットを公開する
+gas_deployment_id=$(grep "^APP_GAS_DEPLOYMENT_ID=" .env | sed 's/APP_GAS_DEPLOYMENT_ID=//')
+api_version=$(grep "^APP_API_VERSION=" .env | sed 's/APP_API_VERSION=//')
+echo "Start snapshot." `date '+%y/%m/%d %H:%M:%S'`
+curl -fsSL -o dist/data.json "https://script.google.com/macros/s/$gas_deployment_id/exec?v=v$api_version"
+if [ $? -eq 0 ] && head -c 1 dist/data.json | grep -q '[[{]'; then
+    # 初回表示を軽くするため、圧縮形式のスナップショットも公開する
+    cargo run --release --example snapshot -- dist/data.json dist/data.bin || rm -f dist/data.bin
+    echo "Completed snapshot." `date '+%y/%m/%d %H:%M:%S'`
+else
+    # スナップショットがなくてもデプロイは続ける
+    echo "Failed snapshot. Skip publishing data.json and data.bin." `date '+%y/%m/%d %H:%M:%S'`
+    rm -f dist/data.json dist/data.bin
+fi
+
 # Push to gh-pages branch
 echo "Start push." `date '+%y/%m/%d %H:%M:%S'`
 git subtree push --prefix dist/ origin gh-pages
@@ -0,0 +1,244 @@
+//! JSON のデータを圧縮形式のスナップショットに変換する。
+//!
+//! `cargo run --release --example snapshot -- dist/data.json dist/data.bin`
+//!
+//! 形式は `src/snapshot.rs` で定義する。アプリ本体は読み込みだけを行うため、変換はここで行う。
+
+use std::collections::HashMap;
+use std::fs;
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+#[path = "../src/snapshot.rs"]
+mod snapshot;
+
+use snapshot::CompactValue;
+
+/** deflate の圧縮レベル (0 から 10) */
+const COMPRESSION_LEVEL: u8 = 10;
+
+#[derive(Default)]
+struct StringTable {
+    strings: Vec<String>,
+    indices: HashMap<String, u32>,
+}
+
+impl StringTable {
+    fn intern(&mut self, value: &str) -> u32 {
+        if let Some(index) = self.indices.get(value) {
+            return *index;
+        }
+        let index = self.strings.len() as u32;
+        self.strings.push(value.to_string());
+        self.indices.insert(value.to_string(), index);
+        index
+    }
+
+    fn compact(&mut self, value: &Value) -> CompactValue {
+        match value {
+            Value::Null => CompactValue::Null,
+            Value::Bool(value) => CompactValue::Bool(*value),
+            Value::Number(value) => CompactValue::Number(self.intern(&value.to_string())),
+            Value::String(value) => CompactValue::String(self.intern(value)),
+            Value::Array(values) => match table_keys(values) {
+                Some(keys) => CompactValue::Table {
+                    columns: keys
+                        .iter()
+                        .map(|key| values.iter().map(|row| self.compact(&row[key])).collect())
+                        .collect(),
+                    keys: keys.iter().map(|key| self.intern(key)).collect(),
+                    rows: values.len() as u32,
+                },
+                None => {
+                    CompactValue::Array(values.iter().map(|value| self.compact(value)).collect())
+                }
+            },
+            Value::Object(map) => CompactValue::Object(
+                map.iter()
+                    .map(|(key, value)| (self.intern(key), self.compact(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// 配列の要素がすべて同じキーを持つオブジェクトの場合にそのキーを返す。
+fn table_keys(values: &[Value]) -> Option<Vec<&String>> {
+    let keys = values
+        .first()?
+        .as_object()?
+        .keys()
+        .collect::<Vec<&String>>();
+    let same_keys = values.iter().all(|value| {
+        value
+            .as_object()
+            .is_some_and(|map| map.len() == keys.len() && map.keys().eq(keys.iter().copied()))
+    });
+    same_keys.then_some(keys)
+}
+
+/// JSON の値をスナップショットの形式に変換する。
+fn encode(value: &Value) -> Result<Vec<u8>> {
+    let mut table = StringTable::default();
+    let root = table.compact(value);
+    let snapshot = snapshot::Snapshot {
+        strings: table.strings,
+        root,
+    };
+    let serialized = postcard::to_allocvec(&snapshot).context("Failed to serialize snapshot")?;
+    let mut bytes = snapshot::MAGIC.to_vec();
+    bytes.extend(miniz_oxide::deflate::compress_to_vec(
+        &serialized,
+        COMPRESSION_LEVEL,
+    ));
+    Ok(bytes)
+}
+
+fn main() -> Result<()> {
+    let args = std::env::args().collect::<Vec<String>>();
+    let [_, input, output] = args.as_slice() else {
+        bail!("Usage: snapshot <input.json> <output.bin>");
+    };
+
+    let json = fs::read_to_string(input).with_context(|| format!("Failed to read {}", input))?;
+    let value: Value =
+        serde_json::from_str(&json).with_context(|| format!("{} is not JSON", input))?;
+    let bytes = encode(&value)?;
+    // 変換したスナップショットが元の JSON に戻ることを確かめてから書き出す
+    if snapshot::decode(&bytes)? != value {
+        bail!("Snapshot does not round-trip to {}", input);
+    }
+    fs::write(output, &bytes).with_context(|| format!("Failed to write {}", output))?;
+
+    println!(
+        "{}: {} bytes -> {}: {} bytes ({:.1}%)",
+        input,
+        json.len(),
+        output,
+        bytes.len(),
+        bytes.len() as f64 / json.len() as f64 * 100.0
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use serde_json::json;
+
+    use super::*;
+
+    /** 開発用のデータ */
+    const FIXTURE_JSON: &str = include_str!("../fixtures/data.json");
+
+    const NOTIFIERS: [&str; 4] = [
+        "株式会社サンプル食品",
+        "あんしん製薬株式会社",
+        "ヘルシーフーズ株式会社",
+        "サンプル飲料株式会社",
+    ];
+
+    const FUNCTIONALITIES: [&str; 3] = [
+        "本品にはGABAが含まれます。GABAには血圧が高めの方の血圧を下げる機能があることが報告されています。",
+        "本品にはDHA・EPAが含まれます。DHA・EPAには中性脂肪を低下させる機能があることが報告されています。",
+        "本品にはL-テアニンが含まれます。L-テアニンには睡眠の質の向上に役立つ機能があることが報告されています。",
+    ];
+
+    const GRADES: [&str; 5] = ["A", "B", "C", "D", "E"];
+
+    const QUALITIES: [&str; 3] = ["高い", "中程度", "低い"];
+
+    const INGREDIENTS: [&str; 3] = ["GABA", "DHA・EPA", "L-テアニン"];
+
+    /// 実際のデータに近い形の v2 のレスポンスを作る。
+    fn synthetic_response(count: usize) -> Value {
+        let records = (0..count)
+            .map(|i| {
+                json!({
+                    "notificationNumber": format!("J{:05}", i),
+                    "productName": format!("サンプル商品{}号", i),
+                    "notifierName": NOTIFIERS[i % NOTIFIERS.len()],
+                    "functionalityToDisplay": FUNCTIONALITIES[i % FUNCTIONALITIES.len()],
+                    "assessment": GRADES[i % GRADES.len()],
+                    "generalReviewOfEvidence": format!(
+                        "研究レビューの質は{}。採用論文{}報のうち{}報で有意差あり。",
+                        QUALITIES[i % QUALITIES.len()],
+                        i % 17 + 1,
+                        i % 7
+                    ),
+                    "functionalIngredient": INGREDIENTS[i % INGREDIENTS.len()],
+                    "notificationDate": format!("2024-{:02}-{:02}", i % 12 + 1, i % 28 + 1),
+                })
+            })
+            .collect::<Vec<Value>>();
+        json!({
+            "schemaVersion": "2.0.0",
+            "generatedAt": "2024-04-01T00:00:00Z",
+            "records": records,
+        })
+    }
+
+    #[test]
+    fn round_trips_fixture() {
+        let value: Value = serde_json::from_str(FIXTURE_JSON).unwrap();
+        assert_eq!(snapshot::decode(&encode(&value).unwrap()).unwrap(), value);
+    }
+
+    #[test]
+    fn round_trips_all_json_types() {
+        let value = json!({
+            "null": null,
+            "bool": [true, false],
+            "numbers": [0, -1, 1.5, 18446744073709551615u64],
+            "strings": ["", "同じ", "同じ", "ｶﾅ"],
+            "nested": {"同じ": {"empty": {}}},
+            "table": [{"a": 1, "b": "同じ"}, {"a": null, "b": "同じ"}],
+            "mixed": [{"a": 1}, {"b": 2}, {"a": 1, "b": 2}, []],
+            "empty": [],
+        });
+        assert_eq!(snapshot::decode(&encode(&value).unwrap()).unwrap(), value);
+    }
+
+    #[test]
+    fn rejects_invalid_snapshots() {
+        assert!(snapshot::decode(b"[]").is_err());
+        assert!(snapshot::decode(b"AMS1broken").is_err());
+    }
+
+    /// 1 万件の合成データで JSON とスナップショットの大きさと読み込み時間を比べる。
+    ///
+    /// 時間がかかるため通常のテストでは実行しない。
+    /// `cargo test --release --example snapshot -- --ignored --nocapture` で計測結果を表示できる。
+    #[test]
+    #[ignore]
+    fn snapshot_size_and_decode_time() {
+        let value = synthetic_response(10_000);
+        let json = serde_json::to_string(&value).unwrap();
+        let bytes = encode(&value).unwrap();
+        let deflated_json = miniz_oxide::deflate::compress_to_vec(json.as_bytes(), 6);
+
+        let start = Instant::now();
+        let from_json: Value = serde_json::from_str(&json).unwrap();
+        let json_time = start.elapsed();
+
+        let start = Instant::now();
+        let from_snapshot = snapshot::decode(&bytes).unwrap();
+        let snapshot_time = start.elapsed();
+
+        println!(
+            "JSON: {} bytes ({} bytes deflated), decode {:?}",
+            json.len(),
+            deflated_json.len(),
+            json_time
+        );
+        println!(
+            "snapshot: {} bytes, decode {:?}",
+            bytes.len(),
+            snapshot_time
+        );
+        assert_eq!(from_snapshot, from_json);
+        assert!(bytes.len() < deflated_json.len());
+    }
+}
@@ -0,0 +1,358 @@
+//! API レスポンスの解釈
+//!
+//! サーバーは `{ "schemaVersion": "2.0.0", "generatedAt": "...", "records": [...] }` の形式でデータを返す。
+//! 封筒のない配列は封筒導入前の v1 のレスポンスとして扱う。
+//! `records` の形式は `schemaVersion` のメジャーバージョンごとに異なるため、対応するデコーダーで読み取る。
+//! 読み取れないレコードがあってもデータ全体は捨てず、そのレコードだけを読み飛ばして `skipped` に記録する。
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::assessment::Assessment;
+use crate::{env, Data};
+
+/** このクライアントが読み取れるスキーマのメジャーバージョンの最大値 */
+pub const MAX_SUPPORTED_MAJOR_VERSION: u64 = 2;
+
+/** サーバーのスキーマがクライアントより新しい */
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnsupportedVersion {
+    pub schemaVersion: String,
+}
+
+impl fmt::Display for UnsupportedVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unsupported schema version {} (client supports up to v{}, APP_API_VERSION {})",
+            self.schemaVersion,
+            MAX_SUPPORTED_MAJOR_VERSION,
+            env::APP_API_VERSION
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedVersion {}
+
+/** 読み取れずに読み飛ばしたレコード */
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SkippedRecord {
+    /** `records` の中での位置 (0 始まり) */
+    pub index: usize,
+    /** 届出番号。読み取れなかった場合は `None` */
+    pub notificationNumber: Option<String>,
+    /** 読み取れなかった項目のパス */
+    pub path: String,
+    pub message: String,
+}
+
+/** 解釈したレスポンス */
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataResponse {
+    pub schemaVersion: String,
+    /** サーバーがデータを生成した日時 */
+    pub generatedAt: Option<String>,
+    pub records: Vec<Data>,
+    pub skipped: Vec<SkippedRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    schemaVersion: String,
+    #[serde(default)]
+    generatedAt: Option<String>,
+    records: Value,
+}
+
+/** v1 のレコード */
+#[derive(Debug, Deserialize)]
+struct RecordV1 {
+    notificationNumber: String,
+    productName: String,
+    notifierName: String,
+    functionalityToDisplay: String,
+    assessment: Assessment,
+    generalReviewOfEvidence: String,
+}
+
+impl From<RecordV1> for Data {
+    fn from(record: RecordV1) -> Self {
+        Self {
+            notificationNumber: record.notificationNumber,
+            productName: record.productName,
+            notifierName: record.notifierName,
+            functionalityToDisplay: record.functionalityToDisplay,
+            assessment: record.assessment,
+            generalReviewOfEvidence: record.generalReviewOfEvidence,
+            functionalIngredient: None,
+            notificationDate: None,
+        }
+    }
+}
+
+/// v2 のレコード。v1 に機能性関与成分名と届出日が加わる。
+///
+/// `#[serde(flatten)]` を使うと読み取れなかった項目のパスが分からなくなるため、v1 の項目も並べて書く。
+#[derive(Debug, Deserialize)]
+struct RecordV2 {
+    notificationNumber: String,
+    productName: String,
+    notifierName: String,
+    functionalityToDisplay: String,
+    assessment: Assessment,
+    generalReviewOfEvidence: String,
+    #[serde(default)]
+    functionalIngredient: Option<String>,
+    #[serde(default)]
+    notificationDate: Option<String>,
+}
+
+impl From<RecordV2> for Data {
+    fn from(record: RecordV2) -> Self {
+        Self {
+            notificationNumber: record.notificationNumber,
+            productName: record.productName,
+            notifierName: record.notifierName,
+            functionalityToDisplay: record.functionalityToDisplay,
+            assessment: record.assessment,
+            generalReviewOfEvidence: record.generalReviewOfEvidence,
+            functionalIngredient: record.functionalIngredient,
+            notificationDate: record.notificationDate,
+        }
+    }
+}
+
+/// `1.2.3` や `v1` のような表記からメジャーバージョンを読み取る。
+fn major_version(version: &str) -> Result<u64> {
+    let version = version.trim().trim_start_matches('v');
+    version
+        .split('.')
+        .next()
+        .and_then(|major| major.parse().ok())
+        .ok_or_else(|| anyhow!("Invalid schema version: {:?}", version))
+}
+
+/// レスポンスの本文を解釈する。
+///
+/// サーバーのメジャーバージョンがクライアントより新しい場合は `UnsupportedVersion` のエラーを返す。
+pub fn decode(body: &str) -> Result<DataResponse> {
+    let value: Value = serde_json::from_str(body).context("Response is not JSON")?;
+    decode_value(value)
+}
+
+/// JSON として読み取り済みのレスポンスを解釈する。
+pub fn decode_value(value: Value) -> Result<DataResponse> {
+    let envelope = match value {
+        Value::Array(_) => Envelope {
+            schemaVersion: "1.0.0".to_string(),
+            generatedAt: None,
+            records: value,
+        },
+        value => serde_json::from_value(value).context("Invalid response envelope")?,
+    };
+
+    let (records, skipped) = match major_version(&envelope.schemaVersion)? {
+        1 => decode_records::<RecordV1>(envelope.records)?,
+        2 => decode_records::<RecordV2>(envelope.records)?,
+        major if major > MAX_SUPPORTED_MAJOR_VERSION => {
+            return Err(UnsupportedVersion {
+                schemaVersion: envelope.schemaVersion,
+            }
+            .into())
+        }
+        _ => {
+            return Err(anyhow!(
+                "Invalid schema version: {:?}",
+                envelope.schemaVersion
+            ))
+        }
+    };
+    Ok(DataResponse {
+        schemaVersion: envelope.schemaVersion,
+        generatedAt: envelope.generatedAt,
+        records,
+        skipped,
+    })
+}
+
+/// レコードを 1 件ずつ読み取る。読み取れないレコードは読み飛ばして返す。
+fn decode_records<T>(records: Value) -> Result<(Vec<Data>, Vec<SkippedRecord>)>
+where
+    T: for<'de> Deserialize<'de> + Into<Data>,
+{
+    let Value::Array(records) = records else {
+        return Err(anyhow!("records is not an array"));
+    };
+    let mut decoded = Vec::with_capacity(records.len());
+    let mut skipped = Vec::new();
+    // 解釈できなかった評価は同じ値ごとに 1 回だけログに出す
+    let mut unknown_assessments = BTreeSet::new();
+    for (index, record) in records.into_iter().enumerate() {
+        let notification_number = record
+            .get("notificationNumber")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        match serde_path_to_error::deserialize::<_, T>(record) {
+            Ok(record) => {
+                let data: Data = record.into();
+                if let Assessment::Unknown(value) = &data.assessment {
+                    if unknown_assessments.insert(value.clone()) {
+                        log::warn!("Unknown assessment: {:?}", value);
+                    }
+                }
+                decoded.push(data);
+            }
+            Err(err) => {
+                let skipped_record = SkippedRecord {
+                    index,
+                    notificationNumber: notification_number,
+                    path: err.path().to_string(),
+                    message: err.into_inner().to_string(),
+                };
+                log::warn!("Skipped record: {:?}", skipped_record);
+                skipped.push(skipped_record);
+            }
+        }
+    }
+    Ok((decoded, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECORD_V1: &str = r#"{
+        "notificationNumber": "X001",
+        "productName": "ギャバ",
+        "notifierName": "サンプル食品",
+        "functionalityToDisplay": "血圧",
+        "assessment": "A",
+        "generalReviewOfEvidence": "総評"
+    }"#;
+
+    const RECORD_V2: &str = r#"{
+        "notificationNumber": "X001",
+        "productName": "ギャバ",
+        "notifierName": "サンプル食品",
+        "functionalityToDisplay": "血圧",
+        "assessment": "A",
+        "generalReviewOfEvidence": "総評",
+        "functionalIngredient": "GABA",
+        "notificationDate": "2024-04-01"
+    }"#;
+
+    #[test]
+    fn decodes_bare_array_as_v1() {
+        let response = decode(&format!("[{}]", RECORD_V1)).unwrap();
+        assert_eq!(response.schemaVersion, "1.0.0");
+        assert_eq!(response.records[0].productName, "ギャバ");
+        assert_eq!(response.records[0].functionalIngredient, None);
+    }
+
+    #[test]
+    fn decodes_v1_envelope() {
+        let body = format!(
+            r#"{{"schemaVersion": "1.1.0", "generatedAt": "2024-04-01T00:00:00Z", "records": [{}]}}"#,
+            RECORD_V1
+        );
+        let response = decode(&body).unwrap();
+        assert_eq!(
+            response.generatedAt.as_deref(),
+            Some("2024-04-01T00:00:00Z")
+        );
+        assert_eq!(response.records.len(), 1);
+    }
+
+    #[test]
+    fn decodes_v2_extra_fields() {
+        let body = format!(
+            r#"{{"schemaVersion": "2.0.0", "records": [{}, {}]}}"#,
+            RECORD_V2, RECORD_V1
+        );
+        let response = decode(&body).unwrap();
+        assert_eq!(
+            response.records[0].functionalIngredient.as_deref(),
+            Some("GABA")
+        );
+        assert_eq!(
+            response.records[0].notificationDate.as_deref(),
+            Some("2024-04-01")
+        );
+        assert_eq!(response.records[1].functionalIngredient, None);
+    }
+
+    #[test]
+    fn rejects_newer_major_version() {
+        let err = decode(r#"{"schemaVersion": "3.0.0", "records": []}"#).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<UnsupportedVersion>(),
+            Some(&UnsupportedVersion {
+                schemaVersion: "3.0.0".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_versions() {
+        assert!(decode(r#"{"schemaVersion": "latest", "records": []}"#).is_err());
+        assert!(decode(r#"{"schemaVersion": "0.9.0", "records": []}"#).is_err());
+        assert_eq!(major_version("v2").unwrap(), 2);
+    }
+
+    #[test]
+    fn skips_malformed_records() {
+        let body = format!(
+            r#"[{}, {{"notificationNumber": "X002", "productName": 1}}, "broken", {}]"#,
+            RECORD_V1, RECORD_V1
+        );
+        let response = decode(&body).unwrap();
+        assert_eq!(response.records.len(), 2);
+        assert_eq!(
+            response.skipped,
+            vec![
+                SkippedRecord {
+                    index: 1,
+                    notificationNumber: Some("X002".to_string()),
+                    path: "productName".to_string(),
+                    message: "invalid type: integer `1`, expected a string".to_string(),
+                },
+                SkippedRecord {
+                    index: 2,
+                    notificationNumber: None,
+                    path: ".".to_string(),
+                    message: "invalid type: string \"broken\", expected struct RecordV1"
+                        .to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_field_paths_in_v2_records() {
+        let body = format!(
+            r#"{{"schemaVersion": "2.0.0", "records": [{}, {}, {}, {{"notificationNumber": "X004", "productName": "ギャバ", "notifierName": "サンプル食品", "functionalityToDisplay": "血圧", "assessment": 1, "generalReviewOfEvidence": "総評"}}, {{"notificationNumber": "X005", "productName": "ギャバ", "notifierName": "サンプル食品", "functionalityToDisplay": "血圧", "assessment": "A", "generalReviewOfEvidence": "総評", "notificationDate": 20240401}}]}}"#,
+            RECORD_V2, RECORD_V2, RECORD_V1
+        );
+        let response = decode(&body).unwrap();
+        assert_eq!(response.records.len(), 3);
+        let paths = response
+            .skipped
+            .iter()
+            .map(|record| (record.index, record.path.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec![(3, "assessment"), (4, "notificationDate")]);
+    }
+
+    #[test]
+    fn reports_missing_fields_in_v2_records() {
+        let body = r#"{"schemaVersion": "2.0.0", "records": [{"notificationNumber": "X003"}]}"#;
+        let response = decode(body).unwrap();
+        assert!(response.records.is_empty());
+        assert_eq!(response.skipped[0].index, 0);
+        assert!(response.skipped[0].message.contains("missing field"));
+    }
+}
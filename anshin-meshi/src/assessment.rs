@@ -0,0 +1,237 @@
+//! ASCON 総合評価判定
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::normalize::normalize;
+
+/** 絞り込み用の評価の区分。解釈できなかった評価はまとめて `Other` とする */
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Grade {
+    A,
+    B,
+    C,
+    D,
+    E,
+    Other,
+}
+
+impl Grade {
+    /** 評価の高い順に並べた区分 */
+    pub const ALL: [Grade; 6] = [
+        Grade::A,
+        Grade::B,
+        Grade::C,
+        Grade::D,
+        Grade::E,
+        Grade::Other,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Grade::A => "A",
+            Grade::B => "B",
+            Grade::C => "C",
+            Grade::D => "D",
+            Grade::E => "E",
+            Grade::Other => "その他",
+        }
+    }
+
+    /** URL のクエリ文字列での表記 */
+    pub fn key(&self) -> &'static str {
+        match self {
+            Grade::Other => "other",
+            grade => grade.label(),
+        }
+    }
+
+    /** Bulma の tag や progress に付ける色のクラス */
+    pub fn tag_class(&self) -> &'static str {
+        match self {
+            Grade::A => "is-success",
+            Grade::B => "is-info",
+            Grade::C => "is-warning",
+            Grade::D => "is-danger is-light",
+            Grade::E => "is-danger",
+            Grade::Other => "is-light",
+        }
+    }
+}
+
+impl FromStr for Grade {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Grade::ALL
+            .into_iter()
+            .find(|grade| grade.key() == s)
+            .ok_or_else(|| format!("unknown grade: {}", s))
+    }
+}
+
+/// 絞り込む評価の区分の集合。空の場合は絞り込まない。
+///
+/// URL のクエリ文字列では `A,B,other` のようにカンマ区切りで表す。
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct GradeFilter(pub BTreeSet<Grade>);
+
+impl GradeFilter {
+    /** 区分が選ばれていない場合はすべての評価に一致する */
+    pub fn matches(&self, grade: Grade) -> bool {
+        self.0.is_empty() || self.0.contains(&grade)
+    }
+}
+
+impl fmt::Display for GradeFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys = self.0.iter().map(Grade::key).collect::<Vec<&str>>();
+        write!(f, "{}", keys.join(","))
+    }
+}
+
+impl FromStr for GradeFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .filter(|key| !key.is_empty())
+            .map(Grade::from_str)
+            .collect::<Result<BTreeSet<Grade>, String>>()
+            .map(GradeFilter)
+    }
+}
+
+/** ASCON 総合評価判定 */
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
+pub enum Assessment {
+    A,
+    B,
+    C,
+    D,
+    E,
+    /** 解釈できなかった評価。元の文字列を保持する */
+    Unknown(String),
+}
+
+impl Assessment {
+    /// 文字列から評価を読み取る。
+    ///
+    /// 全角・小文字の表記や「A評価」のような後ろに続く文字は許容する。
+    pub fn parse(value: &str) -> Self {
+        let normalized = normalize(value.trim());
+        let grade = match normalized.chars().next() {
+            Some('a') => Some(Assessment::A),
+            Some('b') => Some(Assessment::B),
+            Some('c') => Some(Assessment::C),
+            Some('d') => Some(Assessment::D),
+            Some('e') => Some(Assessment::E),
+            _ => None,
+        };
+        // 「AB」のように英字が続く場合は判定とみなさない
+        let is_single_letter = normalized
+            .chars()
+            .nth(1)
+            .is_none_or(|c| !c.is_ascii_alphabetic());
+        match grade {
+            Some(grade) if is_single_letter => grade,
+            _ => Assessment::Unknown(value.to_string()),
+        }
+    }
+
+    pub fn grade(&self) -> Grade {
+        match self {
+            Assessment::A => Grade::A,
+            Assessment::B => Grade::B,
+            Assessment::C => Grade::C,
+            Assessment::D => Grade::D,
+            Assessment::E => Grade::E,
+            Assessment::Unknown(_) => Grade::Other,
+        }
+    }
+
+    /** 表示用の名称 */
+    pub fn label(&self) -> &str {
+        match self {
+            Assessment::A => "A",
+            Assessment::B => "B",
+            Assessment::C => "C",
+            Assessment::D => "D",
+            Assessment::E => "E",
+            Assessment::Unknown(value) if value.trim().is_empty() => "不明",
+            Assessment::Unknown(value) => value,
+        }
+    }
+
+    /** Bulma の tag に付ける色のクラス */
+    pub fn tag_class(&self) -> &'static str {
+        self.grade().tag_class()
+    }
+}
+
+impl From<String> for Assessment {
+    fn from(value: String) -> Self {
+        Assessment::parse(&value)
+    }
+}
+
+impl From<Assessment> for String {
+    fn from(assessment: Assessment) -> Self {
+        assessment.to_string()
+    }
+}
+
+impl fmt::Display for Assessment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Assessment::Unknown(value) => write!(f, "{}", value),
+            grade => write!(f, "{}", grade.label()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Assessment, Grade, GradeFilter};
+
+    #[test]
+    fn parses_grades() {
+        assert_eq!(Assessment::parse("A"), Assessment::A);
+        assert_eq!(Assessment::parse(" ｃ "), Assessment::C);
+        assert_eq!(Assessment::parse("Ｅ評価"), Assessment::E);
+    }
+
+    #[test]
+    fn keeps_unknown_values() {
+        assert_eq!(
+            Assessment::parse("AB"),
+            Assessment::Unknown("AB".to_string())
+        );
+        assert_eq!(
+            Assessment::parse("評価なし"),
+            Assessment::Unknown("評価なし".to_string())
+        );
+        assert_eq!(Assessment::parse("").label(), "不明");
+    }
+
+    #[test]
+    fn round_trips_through_string() {
+        for value in ["A", "D", "評価なし"] {
+            assert_eq!(Assessment::parse(value).to_string(), value);
+        }
+    }
+
+    #[test]
+    fn grade_filter_round_trips_through_query() {
+        let filter = "B,A,other".parse::<GradeFilter>().unwrap();
+        assert_eq!(filter.to_string(), "A,B,other");
+        assert!(filter.matches(Grade::Other));
+        assert!(!filter.matches(Grade::C));
+        assert_eq!("".parse::<GradeFilter>(), Ok(GradeFilter::default()));
+        assert!("Z".parse::<GradeFilter>().is_err());
+    }
+}
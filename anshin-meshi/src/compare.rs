@@ -0,0 +1,160 @@
+//! 商品の比較
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::search::SearchField;
+use crate::Data;
+
+/** 一度に比較できる商品の最大数 */
+pub const MAX_ITEMS: usize = 4;
+
+/// 比較する商品の届出番号。追加した順に並べる。
+///
+/// URL のクエリ文字列では `A1,A2,A3` のようにカンマ区切りで表す。
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct CompareSet(pub Vec<String>);
+
+impl CompareSet {
+    pub fn contains(&self, notification_number: &str) -> bool {
+        self.0.iter().any(|number| number == notification_number)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.0.len() >= MAX_ITEMS
+    }
+
+    /// 商品を追加する。すでに最大数に達している場合は追加せずに `false` を返す。
+    pub fn add(&mut self, notification_number: &str) -> bool {
+        if self.contains(notification_number) {
+            return true;
+        }
+        if self.is_full() {
+            return false;
+        }
+        self.0.push(notification_number.to_string());
+        true
+    }
+
+    pub fn remove(&mut self, notification_number: &str) {
+        self.0.retain(|number| number != notification_number);
+    }
+}
+
+impl fmt::Display for CompareSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join(","))
+    }
+}
+
+impl FromStr for CompareSet {
+    type Err = String;
+
+    /// 空の番号と重複は読み飛ばし、最大数を超えた分は捨てる。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut set = CompareSet::default();
+        for number in s
+            .split(',')
+            .map(str::trim)
+            .filter(|number| !number.is_empty())
+        {
+            set.add(number);
+        }
+        Ok(set)
+    }
+}
+
+/** 比較する項目 */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareField {
+    /** 検索対象の項目 */
+    Search(SearchField),
+    FunctionalIngredient,
+    NotificationDate,
+}
+
+impl CompareField {
+    /** 表示する順に並べた項目。届出番号は列の見出しに表示するため含めない */
+    pub const ALL: [CompareField; 7] = [
+        CompareField::Search(SearchField::ProductName),
+        CompareField::Search(SearchField::NotifierName),
+        CompareField::Search(SearchField::FunctionalityToDisplay),
+        CompareField::Search(SearchField::Assessment),
+        CompareField::Search(SearchField::GeneralReviewOfEvidence),
+        CompareField::FunctionalIngredient,
+        CompareField::NotificationDate,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CompareField::Search(field) => field.label(),
+            CompareField::FunctionalIngredient => "機能性関与成分名",
+            CompareField::NotificationDate => "届出日",
+        }
+    }
+
+    /// 項目の値。API v1 のデータのように値がない場合は `None` を返す。
+    pub fn value(&self, item: &Data) -> Option<String> {
+        match self {
+            CompareField::Search(field) => Some(field.text(item)),
+            CompareField::FunctionalIngredient => item.functionalIngredient.clone(),
+            CompareField::NotificationDate => item.notificationDate.clone(),
+        }
+    }
+}
+
+/// 比較する値が商品によって異なるかどうか。値がない商品も異なるものとして扱う。
+pub fn differs<T: PartialEq>(values: &[Option<T>]) -> bool {
+    values.windows(2).any(|pair| pair[0] != pair[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_query() {
+        let set = "A1,A2,,A1, A3".parse::<CompareSet>().unwrap();
+        assert_eq!(set.0, vec!["A1", "A2", "A3"]);
+        assert_eq!(set.to_string(), "A1,A2,A3");
+        assert_eq!("".parse::<CompareSet>(), Ok(CompareSet::default()));
+    }
+
+    #[test]
+    fn keeps_at_most_max_items() {
+        let mut set = "A1,A2,A3,A4,A5".parse::<CompareSet>().unwrap();
+        assert_eq!(set.0.len(), MAX_ITEMS);
+        assert!(!set.add("A6"));
+        assert!(set.add("A1"));
+        set.remove("A1");
+        assert!(set.add("A6"));
+        assert_eq!(set.0, vec!["A2", "A3", "A4", "A6"]);
+    }
+
+    #[test]
+    fn reads_compared_values() {
+        let item = Data {
+            assessment: crate::assessment::Assessment::B,
+            functionalIngredient: Some("GABA".to_string()),
+            ..Data::sample("A1")
+        };
+        let values = CompareField::ALL
+            .iter()
+            .map(|field| field.value(&item))
+            .collect::<Vec<Option<String>>>();
+        assert_eq!(values[0].as_deref(), Some("商品A1"));
+        assert_eq!(values[3].as_deref(), Some("B"));
+        assert_eq!(values[5].as_deref(), Some("GABA"));
+        assert_eq!(values[6], None);
+    }
+
+    #[test]
+    fn detects_differences() {
+        assert!(!differs(&[Some("A"), Some("A")]));
+        assert!(differs(&[Some("A"), Some("B")]));
+        assert!(differs(&[Some("A"), None]));
+        assert!(!differs::<&str>(&[None]));
+    }
+}
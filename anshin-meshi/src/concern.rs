@@ -0,0 +1,221 @@
+//! 健康の悩みによる商品の分類
+//!
+//! 「血圧」「睡眠」のような悩みごとに商品を探せるよう、`Data::functionalityToDisplay` に
+//! 含まれるキーワードで商品を分類する。分類の辞書は `data/concerns.json` で管理する。
+//!
+//! 辞書の各規則は次の項目を持つ。
+//!
+//! - `id`: URL のクエリ文字列で使う識別子
+//! - `label`: 画面に表示する名前
+//! - `keywords`: いずれかを含む商品をこの悩みに分類するキーワード
+//! - `ignore`: キーワードを探す前に取り除く語句。キーワードが別の意味で使われる語句を指定する
+//! - `examples` / `counterexamples`: 分類される文 / 分類されない文の例。テストで確かめる
+//!
+//! キーワードと表示しようとする機能の文の両方に `normalize` をかけてから比較する。
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+use crate::normalize::normalize;
+use crate::Data;
+
+/** 分類の辞書 */
+const DICTIONARY_JSON: &str = include_str!("../data/concerns.json");
+
+/** 健康の悩みの分類の規則 */
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct Concern {
+    pub id: String,
+    pub label: String,
+    /** 正規化したキーワード */
+    keywords: Vec<String>,
+    /** 正規化した、キーワードを探す前に取り除く語句 */
+    #[serde(default)]
+    ignore: Vec<String>,
+}
+
+impl Concern {
+    /// 正規化した文がこの悩みに分類されるかどうか。
+    fn matches(&self, normalized: &str) -> bool {
+        let mut text = normalized.to_string();
+        for phrase in &self.ignore {
+            text = text.replace(phrase.as_str(), " ");
+        }
+        self.keywords
+            .iter()
+            .any(|keyword| text.contains(keyword.as_str()))
+    }
+}
+
+/// 辞書を読み込み、キーワードを正規化する。
+pub fn parse_dictionary(json: &str) -> Result<Vec<Concern>> {
+    let mut concerns: Vec<Concern> =
+        serde_json::from_str(json).context("Invalid concern dictionary")?;
+    for concern in concerns.iter_mut() {
+        if concern.id.is_empty() || concern.keywords.is_empty() {
+            return Err(anyhow!("Invalid concern: {:?}", concern.label));
+        }
+        concern.keywords = concern.keywords.iter().map(|k| normalize(k)).collect();
+        concern.ignore = concern.ignore.iter().map(|p| normalize(p)).collect();
+    }
+    Ok(concerns)
+}
+
+/// アプリに同梱した辞書。
+pub fn dictionary() -> &'static [Concern] {
+    static DICTIONARY: OnceLock<Vec<Concern>> = OnceLock::new();
+    DICTIONARY.get_or_init(|| {
+        parse_dictionary(DICTIONARY_JSON).expect("bundled concern dictionary is valid")
+    })
+}
+
+/// 識別子から悩みを探す。
+pub fn find(id: &str) -> Option<&'static Concern> {
+    dictionary().iter().find(|concern| concern.id == id)
+}
+
+/// 表示しようとする機能の文を分類する。辞書の順に並べる。
+pub fn classify<'a>(concerns: &'a [Concern], text: &str) -> Vec<&'a Concern> {
+    let normalized = normalize(text);
+    concerns
+        .iter()
+        .filter(|concern| concern.matches(&normalized))
+        .collect()
+}
+
+/** 商品ごとの分類の結果。データの取得時に一度だけ作る */
+#[derive(Debug, Default, PartialEq)]
+pub struct ConcernIndex(HashMap<String, Vec<&'static Concern>>);
+
+impl ConcernIndex {
+    pub fn new(items: &[Data]) -> Self {
+        ConcernIndex(
+            items
+                .iter()
+                .map(|item| {
+                    (
+                        item.notificationNumber.clone(),
+                        classify(dictionary(), &item.functionalityToDisplay),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// 商品の悩み。
+    pub fn concerns_of(&self, notification_number: &str) -> &[&'static Concern] {
+        self.0
+            .get(notification_number)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// 商品が悩みに分類されているかどうか。悩みを指定しない場合はすべての商品に一致する。
+    pub fn matches(&self, notification_number: &str, concern: Option<&Concern>) -> bool {
+        match concern {
+            Some(concern) => self
+                .concerns_of(notification_number)
+                .iter()
+                .any(|c| c.id == concern.id),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /** 辞書の各規則に書いた例 */
+    #[derive(Deserialize)]
+    struct RuleExamples {
+        id: String,
+        examples: Vec<String>,
+        counterexamples: Vec<String>,
+    }
+
+    fn ids(concerns: &[&Concern]) -> Vec<String> {
+        concerns.iter().map(|concern| concern.id.clone()).collect()
+    }
+
+    #[test]
+    fn every_rule_classifies_its_examples() {
+        let rules: Vec<RuleExamples> = serde_json::from_str(DICTIONARY_JSON).unwrap();
+        assert_eq!(rules.len(), dictionary().len());
+        for rule in rules {
+            assert!(!rule.examples.is_empty(), "{}: no examples", rule.id);
+            assert!(
+                !rule.counterexamples.is_empty(),
+                "{}: no counterexamples",
+                rule.id
+            );
+            for example in &rule.examples {
+                assert!(
+                    ids(&classify(dictionary(), example)).contains(&rule.id),
+                    "{} should match {:?}",
+                    rule.id,
+                    example
+                );
+            }
+            for example in &rule.counterexamples {
+                assert!(
+                    !ids(&classify(dictionary(), example)).contains(&rule.id),
+                    "{} should not match {:?}",
+                    rule.id,
+                    example
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ids_are_unique() {
+        let mut ids = dictionary()
+            .iter()
+            .map(|concern| concern.id.as_str())
+            .collect::<Vec<&str>>();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), dictionary().len());
+    }
+
+    #[test]
+    fn normalizes_keywords_and_text() {
+        let concerns = parse_dictionary(
+            r#"[{"id": "digestion", "label": "おなか", "keywords": ["おなか", "BMI"]}]"#,
+        )
+        .unwrap();
+        assert_eq!(ids(&classify(&concerns, "オナカの調子")), vec!["digestion"]);
+        assert_eq!(ids(&classify(&concerns, "ＢＭＩが高め")), vec!["digestion"]);
+        assert!(classify(&concerns, "血圧").is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert!(parse_dictionary("{}").is_err());
+        assert!(parse_dictionary(r#"[{"id": "x", "label": "x", "keywords": []}]"#).is_err());
+    }
+
+    #[test]
+    fn indexes_products_with_several_concerns() {
+        let item = Data {
+            functionalityToDisplay:
+                "一時的な精神的ストレスを緩和し、睡眠の質を高める機能があります。".to_string(),
+            ..Data::sample("A1")
+        };
+        let index = ConcernIndex::new(&[item]);
+        let labels = index
+            .concerns_of("A1")
+            .iter()
+            .map(|concern| concern.label.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(labels, vec!["睡眠", "ストレス・気分"]);
+        assert!(index.matches("A1", find("sleep")));
+        assert!(!index.matches("A1", find("eyes")));
+        assert!(index.matches("A1", None));
+        assert!(index.concerns_of("A2").is_empty());
+    }
+}
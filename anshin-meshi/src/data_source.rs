@@ -0,0 +1,349 @@
+//! データの取得元
+//!
+//! `.env` の `APP_DATA_SOURCE` で取得元を切り替える。
+//! `gas,static,fixture` のようにカンマ区切りで並べると、取得に失敗した場合に次の取得元を試す。
+//!
+//! - `gas`: Google Apps Script のデプロイ (`APP_GAS_DEPLOYMENT_ID`)
+//! - `snapshot`: 圧縮形式のスナップショット (`APP_SNAPSHOT_DATA_URL`)。形式は `snapshot` モジュールを参照
+//! - `static`: 静的な JSON ファイル (`APP_STATIC_DATA_URL`)
+//! - `fixture`: アプリに埋め込んだ開発用のデータ (`fixtures/data.json`)。デバッグビルドでのみ使える
+//!
+//! `APP_STATIC_DATA_URL` と `APP_SNAPSHOT_DATA_URL` に `data.json` のような相対パスを指定すると、
+//! `APP_BASE_PATH` からのパスとして扱う。
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+
+use crate::api::{self, DataResponse};
+use crate::env;
+use crate::retry::{self, TransientError};
+use crate::snapshot;
+
+/** 開発用のデータ。リリースビルドには含めない */
+#[cfg(debug_assertions)]
+const FIXTURE_JSON: &str = include_str!("../fixtures/data.json");
+
+/** Google Apps Script が呼び出し回数や実行時間の上限を超えたときに返すエラーページの文言 */
+const GAS_QUOTA_MESSAGES: [&str; 4] = [
+    "Service invoked too many times",
+    "Exceeded maximum execution time",
+    "サービスの呼び出し回数が多すぎます",
+    "最大実行時間を超えました",
+];
+
+/** データの取得元 */
+pub trait DataSource {
+    /** ログやエラーメッセージに使う取得元の名前 */
+    fn name(&self) -> String;
+
+    async fn fetch(&self) -> Result<DataResponse>;
+}
+
+/** Google Apps Script のデプロイ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct GasDataSource {
+    pub deploymentId: String,
+    pub apiVersion: String,
+}
+
+impl DataSource for GasDataSource {
+    fn name(&self) -> String {
+        "Google Apps Script".to_string()
+    }
+
+    async fn fetch(&self) -> Result<DataResponse> {
+        let url = format!(
+            "https://script.google.com/macros/s/{}/exec?v=v{}",
+            self.deploymentId, self.apiVersion
+        );
+        let body = get(&url).await?.error_for_status()?.text().await?;
+        if is_gas_quota_error(&body) {
+            return Err(TransientError("Google Apps Script quota exceeded".to_string()).into());
+        }
+        api::decode(&body)
+    }
+}
+
+/** 静的な JSON ファイル */
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticJsonDataSource {
+    /** 絶対 URL、またはサイトのオリジンからのパス */
+    pub url: String,
+}
+
+impl DataSource for StaticJsonDataSource {
+    fn name(&self) -> String {
+        format!("JSON スナップショット ({})", self.url)
+    }
+
+    async fn fetch(&self) -> Result<DataResponse> {
+        let body = get(&absolute_url(&self.url)?)
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        api::decode(&body)
+    }
+}
+
+/** 圧縮形式のスナップショット */
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotDataSource {
+    /** 絶対 URL、またはサイトのオリジンからのパス */
+    pub url: String,
+}
+
+impl DataSource for SnapshotDataSource {
+    fn name(&self) -> String {
+        format!("圧縮スナップショット ({})", self.url)
+    }
+
+    async fn fetch(&self) -> Result<DataResponse> {
+        let bytes = get(&absolute_url(&self.url)?)
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        api::decode_value(snapshot::decode(&bytes)?)
+    }
+}
+
+/** アプリに埋め込んだ開発用のデータ。リリースビルドでは常に取得に失敗する */
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixtureDataSource;
+
+impl DataSource for FixtureDataSource {
+    fn name(&self) -> String {
+        "アプリに同梱したサンプルデータ".to_string()
+    }
+
+    #[cfg(debug_assertions)]
+    async fn fetch(&self) -> Result<DataResponse> {
+        api::decode(FIXTURE_JSON)
+    }
+
+    #[cfg(not(debug_assertions))]
+    async fn fetch(&self) -> Result<DataResponse> {
+        Err(anyhow!("The fixture is not bundled in release builds"))
+    }
+}
+
+/** 設定で選べる取得元 */
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnyDataSource {
+    Gas(GasDataSource),
+    StaticJson(StaticJsonDataSource),
+    Snapshot(SnapshotDataSource),
+    Fixture(FixtureDataSource),
+}
+
+impl AnyDataSource {
+    /// `APP_DATA_SOURCE` の値から取得元を作る。
+    pub fn from_config(kind: &str) -> Result<Self> {
+        match kind {
+            "gas" => Ok(AnyDataSource::Gas(GasDataSource {
+                deploymentId: env::APP_GAS_DEPLOYMENT_ID.to_string(),
+                apiVersion: env::APP_API_VERSION.to_string(),
+            })),
+            "static" => Ok(AnyDataSource::StaticJson(StaticJsonDataSource {
+                url: site_path(env::APP_BASE_PATH, env::APP_STATIC_DATA_URL),
+            })),
+            "snapshot" => Ok(AnyDataSource::Snapshot(SnapshotDataSource {
+                url: site_path(env::APP_BASE_PATH, env::APP_SNAPSHOT_DATA_URL),
+            })),
+            "fixture" if cfg!(debug_assertions) => Ok(AnyDataSource::Fixture(FixtureDataSource)),
+            _ => Err(anyhow!("Unknown data source: {:?}", kind)),
+        }
+    }
+}
+
+impl DataSource for AnyDataSource {
+    fn name(&self) -> String {
+        match self {
+            AnyDataSource::Gas(source) => source.name(),
+            AnyDataSource::StaticJson(source) => source.name(),
+            AnyDataSource::Snapshot(source) => source.name(),
+            AnyDataSource::Fixture(source) => source.name(),
+        }
+    }
+
+    async fn fetch(&self) -> Result<DataResponse> {
+        match self {
+            AnyDataSource::Gas(source) => source.fetch().await,
+            AnyDataSource::StaticJson(source) => source.fetch().await,
+            AnyDataSource::Snapshot(source) => source.fetch().await,
+            AnyDataSource::Fixture(source) => source.fetch().await,
+        }
+    }
+}
+
+/// `.env` で設定した取得元を試す順に返す。
+pub fn configured() -> Vec<AnyDataSource> {
+    from_config_list(env::APP_DATA_SOURCE)
+}
+
+/// 最初に試す取得元を返す。
+pub fn primary() -> AnyDataSource {
+    configured().swap_remove(0)
+}
+
+/// カンマ区切りの取得元の一覧を読み取る。
+///
+/// 不正な取得元は読み飛ばし、有効な取得元が 1 つもない場合は Google Apps Script を使う。
+fn from_config_list(value: &str) -> Vec<AnyDataSource> {
+    let sources = value
+        .split(',')
+        .map(str::trim)
+        .filter(|kind| !kind.is_empty())
+        .filter_map(|kind| match AnyDataSource::from_config(kind) {
+            Ok(source) => Some(source),
+            Err(err) => {
+                log::error!("{:?}", err);
+                None
+            }
+        })
+        .collect::<Vec<AnyDataSource>>();
+    if sources.is_empty() {
+        return vec![AnyDataSource::from_config("gas").expect("gas is a valid data source")];
+    }
+    sources
+}
+
+/// `APP_REQUEST_TIMEOUT_MS` の時間で打ち切る GET リクエストを送る。
+async fn get(url: &str) -> reqwest::Result<reqwest::Response> {
+    let timeout = retry::parse_config(
+        "APP_REQUEST_TIMEOUT_MS",
+        env::APP_REQUEST_TIMEOUT_MS,
+        15_000,
+    );
+    reqwest::Client::new()
+        .get(url)
+        .timeout(Duration::from_millis(timeout))
+        .send()
+        .await
+}
+
+/// レスポンスが JSON ではなく、Google Apps Script の上限超過のエラーページかどうか。
+fn is_gas_quota_error(body: &str) -> bool {
+    let body = body.trim_start();
+    !body.starts_with('{')
+        && !body.starts_with('[')
+        && GAS_QUOTA_MESSAGES
+            .iter()
+            .any(|message| body.contains(message))
+}
+
+/// 相対パスを `base_path` からのパスにする。絶対 URL とオリジンからのパスはそのまま返す。
+fn site_path(base_path: &str, url: &str) -> String {
+    if url.starts_with('/') || url.starts_with("http://") || url.starts_with("https://") {
+        return url.to_string();
+    }
+    format!("{}/{}", base_path.trim_end_matches('/'), url)
+}
+
+/// サイトのオリジンからのパスを絶対 URL にする。
+///
+/// reqwest は相対 URL を扱えないため、ブラウザの `location.origin` を補う。
+fn absolute_url(url: &str) -> Result<String> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        return Ok(url.to_string());
+    }
+    let origin = web_sys::window()
+        .ok_or_else(|| anyhow!("window is not available"))?
+        .location()
+        .origin()
+        .map_err(|err| anyhow!("Failed to get location.origin: {:?}", err))?;
+    Ok(format!("{}{}", origin, url))
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn fixture_is_valid_data() {
+        let response = block_on(FixtureDataSource.fetch()).unwrap();
+        assert!(!response.records.is_empty());
+    }
+
+    #[test]
+    fn selects_source_from_config() {
+        assert!(matches!(
+            AnyDataSource::from_config("gas"),
+            Ok(AnyDataSource::Gas(_))
+        ));
+        assert!(matches!(
+            AnyDataSource::from_config("static"),
+            Ok(AnyDataSource::StaticJson(_))
+        ));
+        assert!(matches!(
+            AnyDataSource::from_config("snapshot"),
+            Ok(AnyDataSource::Snapshot(_))
+        ));
+        #[cfg(debug_assertions)]
+        assert_eq!(
+            AnyDataSource::from_config("fixture").unwrap(),
+            AnyDataSource::Fixture(FixtureDataSource)
+        );
+        #[cfg(not(debug_assertions))]
+        assert!(AnyDataSource::from_config("fixture").is_err());
+        assert!(AnyDataSource::from_config("ftp").is_err());
+    }
+
+    #[test]
+    fn reads_ordered_source_list() {
+        let sources = from_config_list("gas, static,ftp,snapshot");
+        assert_eq!(sources.len(), 3);
+        assert!(matches!(sources[0], AnyDataSource::Gas(_)));
+        assert!(matches!(sources[1], AnyDataSource::StaticJson(_)));
+        assert!(matches!(sources[2], AnyDataSource::Snapshot(_)));
+        assert!(matches!(
+            from_config_list("").as_slice(),
+            [AnyDataSource::Gas(_)]
+        ));
+    }
+
+    #[test]
+    fn detects_gas_quota_errors() {
+        assert!(is_gas_quota_error(
+            "<!DOCTYPE html><html><body>Service invoked too many times for one day: urlfetch.</body></html>"
+        ));
+        assert!(is_gas_quota_error(
+            "<html><body>Exceeded maximum execution time</body></html>"
+        ));
+        assert!(!is_gas_quota_error("<html><body>Not Found</body></html>"));
+        assert!(!is_gas_quota_error(
+            r#"[{"productName": "Service invoked too many times"}]"#
+        ));
+    }
+
+    #[test]
+    fn resolves_relative_paths_from_base_path() {
+        assert_eq!(
+            site_path("/anshin-meshi", "data.json"),
+            "/anshin-meshi/data.json"
+        );
+        assert_eq!(site_path("/app/", "data.bin"), "/app/data.bin");
+        assert_eq!(site_path("", "data.json"), "/data.json");
+        assert_eq!(
+            site_path("/anshin-meshi", "/other/data.json"),
+            "/other/data.json"
+        );
+        assert_eq!(
+            site_path("/anshin-meshi", "https://example.com/data.json"),
+            "https://example.com/data.json"
+        );
+    }
+
+    #[test]
+    fn keeps_absolute_urls() {
+        assert_eq!(
+            absolute_url("https://example.com/data.json").unwrap(),
+            "https://example.com/data.json"
+        );
+    }
+}
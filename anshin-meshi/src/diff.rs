@@ -0,0 +1,380 @@
+//! データの差分
+//!
+//! レコードの項目ごとのハッシュを指紋とし、届出番号ごとに比べて追加・更新・削除を数える。
+//!
+//! 前回の訪問で見たデータの指紋を localStorage に保存しておき、次の訪問で項目ごとの変更を示す。
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Data;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// FNV-1a ハッシュ。実行環境やバージョンによらず同じ値になる。
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/** 新しいデータでの変更の件数 */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UpdateSummary {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+impl UpdateSummary {
+    pub fn is_empty(&self) -> bool {
+        self.added == 0 && self.updated == 0 && self.removed == 0
+    }
+}
+
+/** 変更を比べる項目 */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordField {
+    ProductName,
+    NotifierName,
+    FunctionalityToDisplay,
+    Assessment,
+    GeneralReviewOfEvidence,
+    FunctionalIngredient,
+    NotificationDate,
+}
+
+impl RecordField {
+    /** 表示する順に並べた項目。`RecordFingerprint::hashes` もこの順に並べる */
+    pub const ALL: [RecordField; 7] = [
+        RecordField::ProductName,
+        RecordField::NotifierName,
+        RecordField::FunctionalityToDisplay,
+        RecordField::Assessment,
+        RecordField::GeneralReviewOfEvidence,
+        RecordField::FunctionalIngredient,
+        RecordField::NotificationDate,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RecordField::ProductName => "商品名",
+            RecordField::NotifierName => "届出者名",
+            RecordField::FunctionalityToDisplay => "表示しようとする機能性",
+            RecordField::Assessment => "ASCON 総合評価判定",
+            RecordField::GeneralReviewOfEvidence => "論文採用の根拠/機能性エビデンスの総評",
+            RecordField::FunctionalIngredient => "機能性関与成分名",
+            RecordField::NotificationDate => "届出日",
+        }
+    }
+
+    fn value(&self, item: &Data) -> Option<String> {
+        match self {
+            RecordField::ProductName => Some(item.productName.clone()),
+            RecordField::NotifierName => Some(item.notifierName.clone()),
+            RecordField::FunctionalityToDisplay => Some(item.functionalityToDisplay.clone()),
+            RecordField::Assessment => Some(item.assessment.to_string()),
+            RecordField::GeneralReviewOfEvidence => Some(item.generalReviewOfEvidence.clone()),
+            RecordField::FunctionalIngredient => item.functionalIngredient.clone(),
+            RecordField::NotificationDate => item.notificationDate.clone(),
+        }
+    }
+}
+
+/// 項目の値のハッシュ。値がない場合は 0 とする。
+fn field_hash(value: Option<&str>) -> u32 {
+    value.map_or(0, |value| fnv1a(value.as_bytes()) as u32)
+}
+
+/// 保存用のレコードの指紋。
+///
+/// 変更前の値を表示できるよう商品名と評価はそのまま保持し、長い項目はハッシュだけを保持する。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RecordFingerprint {
+    pub productName: String,
+    pub assessment: String,
+    /** `RecordField::ALL` の順に並べた各項目のハッシュ */
+    pub hashes: Vec<u32>,
+}
+
+impl RecordFingerprint {
+    pub fn new(item: &Data) -> Self {
+        Self {
+            productName: item.productName.clone(),
+            assessment: item.assessment.to_string(),
+            hashes: RecordField::ALL
+                .iter()
+                .map(|field| field_hash(field.value(item).as_deref()))
+                .collect(),
+        }
+    }
+
+    /// 保持している変更前の値。ハッシュだけを保持している項目は `None` を返す。
+    fn value(&self, field: RecordField) -> Option<String> {
+        match field {
+            RecordField::ProductName => Some(self.productName.clone()),
+            RecordField::Assessment => Some(self.assessment.clone()),
+            _ => None,
+        }
+    }
+}
+
+/** ある時点のデータの指紋 */
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct FingerprintSet {
+    /** データを取得した日時 (UNIX 時間のミリ秒) */
+    pub savedAt: f64,
+    /** 届出番号ごとの指紋 */
+    pub records: BTreeMap<String, RecordFingerprint>,
+}
+
+impl FingerprintSet {
+    pub fn new(items: &[Data], saved_at: f64) -> Self {
+        Self {
+            savedAt: saved_at,
+            records: items
+                .iter()
+                .map(|item| {
+                    (
+                        item.notificationNumber.clone(),
+                        RecordFingerprint::new(item),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/** 変更された項目 */
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: RecordField,
+    /** 変更前の値。保存していない項目は `None` */
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/** 変更されたレコード */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangedRecord {
+    pub item: Data,
+    pub changes: Vec<FieldChange>,
+}
+
+/** 削除されたレコード */
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemovedRecord {
+    pub notificationNumber: String,
+    pub productName: String,
+    pub assessment: String,
+}
+
+/** 前回見たデータからの変更 */
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VisitDiff {
+    /** 比べた前回のデータを取得した日時 (UNIX 時間のミリ秒) */
+    pub since: f64,
+    pub added: Vec<Data>,
+    pub changed: Vec<ChangedRecord>,
+    pub removed: Vec<RemovedRecord>,
+}
+
+impl VisitDiff {
+    pub fn len(&self) -> usize {
+        self.added.len() + self.changed.len() + self.removed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 古いデータと新しいデータを届出番号ごとに比べ、変更の件数を数える。
+///
+/// 訪問ごとの差分と同じく、項目ごとのハッシュ (`RecordFingerprint`) で比べる。
+pub fn summarize(old: &[Data], new: &[Data]) -> UpdateSummary {
+    let diff = diff_since(&FingerprintSet::new(old, 0.0), new);
+    UpdateSummary {
+        added: diff.added.len(),
+        updated: diff.changed.len(),
+        removed: diff.removed.len(),
+    }
+}
+
+/// 前回のデータの指紋と今回のデータを比べる。
+pub fn diff_since(previous: &FingerprintSet, items: &[Data]) -> VisitDiff {
+    let mut diff = VisitDiff {
+        since: previous.savedAt,
+        ..VisitDiff::default()
+    };
+    for item in items {
+        let Some(before) = previous.records.get(&item.notificationNumber) else {
+            diff.added.push(item.clone());
+            continue;
+        };
+        let after = RecordFingerprint::new(item);
+        let changes = RecordField::ALL
+            .iter()
+            .zip(before.hashes.iter().zip(after.hashes.iter()))
+            .filter(|(_, (before, after))| before != after)
+            .map(|(field, _)| FieldChange {
+                field: *field,
+                before: before.value(*field),
+                after: field.value(item),
+            })
+            .collect::<Vec<FieldChange>>();
+        if !changes.is_empty() {
+            diff.changed.push(ChangedRecord {
+                item: item.clone(),
+                changes,
+            });
+        }
+    }
+    let numbers = items
+        .iter()
+        .map(|item| item.notificationNumber.as_str())
+        .collect::<HashSet<&str>>();
+    diff.removed = previous
+        .records
+        .iter()
+        .filter(|(number, _)| !numbers.contains(number.as_str()))
+        .map(|(number, fingerprint)| RemovedRecord {
+            notificationNumber: number.clone(),
+            productName: fingerprint.productName.clone(),
+            assessment: fingerprint.assessment.clone(),
+        })
+        .collect();
+    diff
+}
+
+/// 訪問ごとに見たデータの指紋の履歴。
+///
+/// 直近に見たデータ (`latest`) と、その 1 つ前に見た異なるデータ (`baseline`) を保持する。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct VisitHistory {
+    pub baseline: Option<FingerprintSet>,
+    pub latest: Option<FingerprintSet>,
+}
+
+impl VisitHistory {
+    /// 取得したデータを記録し、前回までに見たデータとの差分を返す。
+    ///
+    /// データが前回から変わっていない場合は、その前の変更を引き続き返す。
+    /// 初めての訪問では比べるデータがないため `None` を返す。
+    pub fn record(&mut self, items: &[Data], fetched_at: f64) -> Option<VisitDiff> {
+        let current = FingerprintSet::new(items, fetched_at);
+        let unchanged = self
+            .latest
+            .as_ref()
+            .is_some_and(|latest| latest.records == current.records);
+        if !unchanged {
+            self.baseline = self.latest.take();
+            self.latest = Some(current);
+        }
+        self.baseline
+            .as_ref()
+            .map(|baseline| diff_since(baseline, items))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assessment::Assessment;
+
+    fn data(number: &str, assessment: &str) -> Data {
+        Data {
+            assessment: Assessment::parse(assessment),
+            ..Data::sample(number)
+        }
+    }
+
+    #[test]
+    fn reports_field_level_changes() {
+        let previous = FingerprintSet::new(&[data("A1", "A"), data("A2", "B")], 1.0);
+        let mut regraded = data("A2", "D");
+        regraded.generalReviewOfEvidence = "新しい総評".to_string();
+        let diff = diff_since(&previous, &[regraded.clone(), data("A3", "C")]);
+
+        assert_eq!(diff.since, 1.0);
+        assert_eq!(diff.added, vec![data("A3", "C")]);
+        assert_eq!(
+            diff.changed,
+            vec![ChangedRecord {
+                item: regraded,
+                changes: vec![
+                    FieldChange {
+                        field: RecordField::Assessment,
+                        before: Some("B".to_string()),
+                        after: Some("D".to_string()),
+                    },
+                    FieldChange {
+                        field: RecordField::GeneralReviewOfEvidence,
+                        before: None,
+                        after: Some("新しい総評".to_string()),
+                    },
+                ],
+            }]
+        );
+        assert_eq!(
+            diff.removed,
+            vec![RemovedRecord {
+                notificationNumber: "A1".to_string(),
+                productName: "商品A1".to_string(),
+                assessment: "A".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn keeps_last_changes_until_data_changes_again() {
+        let mut history = VisitHistory::default();
+        assert_eq!(history.record(&[data("A1", "A")], 1.0), None);
+
+        let diff = history.record(&[data("A1", "B")], 2.0).unwrap();
+        assert_eq!(diff.changed.len(), 1);
+        // 同じデータで再訪問しても前回の変更を表示する
+        assert_eq!(history.record(&[data("A1", "B")], 3.0), Some(diff));
+
+        let diff = history
+            .record(&[data("A1", "B"), data("A2", "C")], 4.0)
+            .unwrap();
+        assert_eq!(diff.since, 2.0);
+        assert_eq!(diff.added.len(), 1);
+        assert!(diff.changed.is_empty());
+    }
+
+    #[test]
+    fn fnv1a_matches_reference_values() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn counts_added_updated_and_removed_records() {
+        let old = vec![data("A1", "A"), data("A2", "B"), data("A3", "C")];
+        let new = vec![data("A1", "A"), data("A2", "D"), data("A4", "E")];
+        assert_eq!(
+            summarize(&old, &new),
+            UpdateSummary {
+                added: 1,
+                updated: 1,
+                removed: 1,
+            }
+        );
+        assert!(summarize(&old, &old).is_empty());
+
+        let mut with_ingredient = data("A1", "A");
+        with_ingredient.functionalIngredient = Some("GABA".to_string());
+        assert_eq!(
+            summarize(&[data("A1", "A")], &[with_ingredient]),
+            UpdateSummary {
+                added: 0,
+                updated: 1,
+                removed: 0,
+            }
+        );
+    }
+}
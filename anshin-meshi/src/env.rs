@@ -1,7 +1,15 @@
 // This file is automatically generated by build.rs
 
-pub const APP_VERSION: &'static str = "0.1.0";
-pub const APP_API_VERSION: &'static str = "1.0.0";
-pub const APP_GAS_DEPLOYMENT_ID: &'static str = "AKfycbw2-g7PbX1lr7C-keieCtPo7t6iRtV3V8WdkUximh6UFXn6mlJxceNVItGV7VhS8GMqTg";
-pub const APP_GITHUB_URL: &'static str = "https://github.com/st-little/anshin-meshi";
-pub const APP_GA_TRACKING_ID: &'static str = "G-QK8F1Y6VFN";
+pub const APP_VERSION: &str = "0.1.0";
+pub const APP_API_VERSION: &str = "1.0.0";
+pub const APP_GAS_DEPLOYMENT_ID: &str = "AKfycbw2-g7PbX1lr7C-keieCtPo7t6iRtV3V8WdkUximh6UFXn6mlJxceNVItGV7VhS8GMqTg";
+pub const APP_GITHUB_URL: &str = "https://github.com/st-little/anshin-meshi";
+pub const APP_GA_TRACKING_ID: &str = "G-QK8F1Y6VFN";
+pub const APP_BASE_PATH: &str = "/anshin-meshi";
+pub const APP_DATA_SOURCE: &str = "gas,snapshot,static";
+pub const APP_STATIC_DATA_URL: &str = "data.json";
+pub const APP_SNAPSHOT_DATA_URL: &str = "data.bin";
+pub const APP_REQUEST_TIMEOUT_MS: &str = "15000";
+pub const APP_MAX_RETRIES: &str = "3";
+pub const APP_RETRY_BASE_DELAY_MS: &str = "1000";
+pub const APP_REFRESH_INTERVAL_MS: &str = "1800000";
@@ -0,0 +1,183 @@
+//! お気に入り
+//!
+//! お気に入りにした商品の届出番号を localStorage に保存する。
+//! 家族の端末の間で共有できるよう JSON ファイルに書き出し、読み込めるようにする。
+
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::Data;
+
+/** 書き出すファイルの形式を識別する名前 */
+const EXPORT_FORMAT: &str = "anshin-meshi-favorites";
+
+/** 書き出すファイルの形式のバージョン */
+const EXPORT_VERSION: u32 = 1;
+
+/** お気に入りにした商品 */
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Favorite {
+    pub notificationNumber: String,
+    /** お気に入りにしたときの商品名。データから商品が見つからなくなった場合の表示に使う */
+    #[serde(default)]
+    pub productName: String,
+}
+
+/** お気に入りの一覧。追加した順に並べる */
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Favorites(pub Vec<Favorite>);
+
+/** 書き出すファイルの内容 */
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportFile {
+    format: String,
+    version: u32,
+    favorites: Vec<Favorite>,
+}
+
+impl Favorites {
+    pub fn contains(&self, notification_number: &str) -> bool {
+        self.0
+            .iter()
+            .any(|favorite| favorite.notificationNumber == notification_number)
+    }
+
+    /// お気に入りでなければ追加し、お気に入りであれば外す。
+    pub fn toggle(&mut self, item: &Data) {
+        if self.contains(&item.notificationNumber) {
+            self.remove(&item.notificationNumber);
+        } else {
+            self.0.push(Favorite {
+                notificationNumber: item.notificationNumber.clone(),
+                productName: item.productName.clone(),
+            });
+        }
+    }
+
+    pub fn remove(&mut self, notification_number: &str) {
+        self.0
+            .retain(|favorite| favorite.notificationNumber != notification_number);
+    }
+
+    /// 読み込んだお気に入りを追加する。すでにあるお気に入りは重複させない。
+    ///
+    /// 追加した件数を返す。
+    pub fn merge(&mut self, favorites: Vec<Favorite>) -> usize {
+        let before = self.0.len();
+        for favorite in favorites {
+            if !self.contains(&favorite.notificationNumber) {
+                self.0.push(favorite);
+            }
+        }
+        self.0.len() - before
+    }
+
+    /// データから見つからなくなったお気に入り。
+    pub fn missing<'a>(&'a self, items: &[Data]) -> Vec<&'a Favorite> {
+        let numbers = items
+            .iter()
+            .map(|item| item.notificationNumber.as_str())
+            .collect::<HashSet<&str>>();
+        self.0
+            .iter()
+            .filter(|favorite| !numbers.contains(favorite.notificationNumber.as_str()))
+            .collect()
+    }
+
+    /// 共有用の JSON ファイルの内容を作る。
+    pub fn export(&self) -> String {
+        let file = ExportFile {
+            format: EXPORT_FORMAT.to_string(),
+            version: EXPORT_VERSION,
+            favorites: self.0.clone(),
+        };
+        serde_json::to_string_pretty(&file).expect("favorites are always serializable")
+    }
+}
+
+/// 共有用の JSON ファイルを読み込む。
+///
+/// 書き出したファイルのほか、届出番号の配列も受け付ける。
+pub fn parse_import(json: &str) -> Result<Vec<Favorite>> {
+    let value: Value = serde_json::from_str(json).context("File is not JSON")?;
+    let favorites = match value {
+        Value::Array(numbers) => numbers
+            .into_iter()
+            .map(|number| match number {
+                Value::String(number) => Ok(Favorite {
+                    notificationNumber: number,
+                    productName: String::new(),
+                }),
+                value => Err(anyhow!("Invalid notification number: {}", value)),
+            })
+            .collect::<Result<Vec<Favorite>>>()?,
+        value => {
+            let file: ExportFile =
+                serde_json::from_value(value).context("Invalid favorites file")?;
+            if file.format != EXPORT_FORMAT {
+                return Err(anyhow!("Unknown format: {:?}", file.format));
+            }
+            if file.version > EXPORT_VERSION {
+                return Err(anyhow!("Unsupported version: {}", file.version));
+            }
+            file.favorites
+        }
+    };
+    Ok(favorites
+        .into_iter()
+        .filter(|favorite| !favorite.notificationNumber.trim().is_empty())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggles_favorites() {
+        let mut favorites = Favorites::default();
+        favorites.toggle(&Data::sample("A1"));
+        favorites.toggle(&Data::sample("A2"));
+        assert!(favorites.contains("A1"));
+        favorites.toggle(&Data::sample("A1"));
+        assert!(!favorites.contains("A1"));
+        assert_eq!(favorites.0[0].productName, "商品A2");
+    }
+
+    #[test]
+    fn round_trips_export_file() {
+        let mut favorites = Favorites::default();
+        favorites.toggle(&Data::sample("A1"));
+        favorites.toggle(&Data::sample("A2"));
+        assert_eq!(parse_import(&favorites.export()).unwrap(), favorites.0);
+    }
+
+    #[test]
+    fn merges_without_duplicates() {
+        let mut favorites = Favorites::default();
+        favorites.toggle(&Data::sample("A1"));
+        let imported = parse_import(r#"["A1", "A3", " "]"#).unwrap();
+        assert_eq!(favorites.merge(imported), 1);
+        assert_eq!(favorites.0.len(), 2);
+    }
+
+    #[test]
+    fn rejects_unknown_files() {
+        assert!(parse_import("not json").is_err());
+        assert!(parse_import(r#"{"format": "other", "version": 1, "favorites": []}"#).is_err());
+        assert!(parse_import(r#"[1, 2]"#).is_err());
+    }
+
+    #[test]
+    fn flags_missing_favorites() {
+        let mut favorites = Favorites::default();
+        favorites.toggle(&Data::sample("A1"));
+        favorites.toggle(&Data::sample("A2"));
+        let missing = favorites.missing(&[Data::sample("A2")]);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].notificationNumber, "A1");
+    }
+}
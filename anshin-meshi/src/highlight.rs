@@ -0,0 +1,165 @@
+//! 検索語の強調表示
+//!
+//! 検索は正規化した文字列で行うため、一致した位置を `NormalizedText` で元の文字列の位置に戻してから
+//! `<mark>` で囲む部分と囲まない部分に分ける。
+
+use std::ops::Range;
+
+use crate::normalize::{normalize, NormalizedText};
+use crate::search::match_ranges;
+
+/** 表示する文字列の一部 */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub text: String,
+    /** 検索語に一致した部分かどうか */
+    pub marked: bool,
+}
+
+/// 検索語に一致する元の文字列の範囲。
+pub fn find(text: &str, query: &str) -> Vec<Range<usize>> {
+    let normalized = NormalizedText::new(text);
+    match_ranges(&normalized.text, &normalize(query))
+        .into_iter()
+        .filter_map(|range| normalized.original_range(range))
+        .collect()
+}
+
+/// 文字列を一致範囲で区切る。重なる範囲は前の範囲を優先する。
+pub fn segments(text: &str, ranges: &[Range<usize>]) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut cursor = 0;
+    for range in ranges {
+        if range.start < cursor || range.end > text.len() {
+            continue;
+        }
+        if range.start > cursor {
+            segments.push(Segment {
+                text: text[cursor..range.start].to_string(),
+                marked: false,
+            });
+        }
+        segments.push(Segment {
+            text: text[range.clone()].to_string(),
+            marked: true,
+        });
+        cursor = range.end;
+    }
+    if cursor < text.len() {
+        segments.push(Segment {
+            text: text[cursor..].to_string(),
+            marked: false,
+        });
+    }
+    segments
+}
+
+/// 最初の一致範囲の前後 `context` 文字を切り出す。切り詰めた側には「…」を付ける。
+pub fn snippet(text: &str, ranges: &[Range<usize>], context: usize) -> Vec<Segment> {
+    let Some(first) = ranges.first() else {
+        return Vec::new();
+    };
+    let start = match context {
+        0 => first.start,
+        _ => text[..first.start]
+            .char_indices()
+            .rev()
+            .nth(context - 1)
+            .map(|(offset, _)| offset)
+            .unwrap_or(0),
+    };
+    let end = text[first.end..]
+        .char_indices()
+        .nth(context)
+        .map(|(offset, _)| first.end + offset)
+        .unwrap_or(text.len());
+
+    let shifted = ranges
+        .iter()
+        .filter(|range| range.start >= start && range.end <= end)
+        .map(|range| range.start - start..range.end - start)
+        .collect::<Vec<Range<usize>>>();
+    let mut segments = segments(&text[start..end], &shifted);
+    if start > 0 {
+        segments.insert(
+            0,
+            Segment {
+                text: "…".to_string(),
+                marked: false,
+            },
+        );
+    }
+    if end < text.len() {
+        segments.push(Segment {
+            text: "…".to_string(),
+            marked: false,
+        });
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(segments: &[Segment]) -> String {
+        segments
+            .iter()
+            .map(|segment| {
+                if segment.marked {
+                    format!("[{}]", segment.text)
+                } else {
+                    segment.text.clone()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn marks_matches_in_original_text() {
+        let text = "ギャバ粒とｷﾞｬﾊﾞ茶";
+        assert_eq!(
+            render(&segments(text, &find(text, "ぎゃば"))),
+            "[ギャバ]粒と[ｷﾞｬﾊﾞ]茶"
+        );
+        assert_eq!(render(&segments(text, &find(text, "なし"))), text);
+        assert_eq!(render(&segments(text, &find(text, ""))), text);
+    }
+
+    #[test]
+    fn maps_search_offsets_back_to_original_text() {
+        let text = "ブルーベリー";
+        assert_eq!(
+            render(&segments(text, &find(text, "ぶるべり"))),
+            "[ブルーベリー]"
+        );
+        assert_eq!(
+            render(&segments(text, &find(text, "ぶる"))),
+            "[ブルー]ベリー"
+        );
+        assert_eq!(
+            render(&segments("ブルーベリー粒", &find("ブルーベリー粒", "リ粒"))),
+            "ブルーベ[リー粒]"
+        );
+    }
+
+    #[test]
+    fn cuts_snippet_around_first_match() {
+        let text =
+            "本品には難消化性デキストリンが含まれるので、食後の血糖値の上昇をおだやかにします。";
+        let ranges = find(text, "血糖値");
+        assert_eq!(
+            render(&snippet(text, &ranges, 4)),
+            "…、食後の[血糖値]の上昇を…"
+        );
+        assert_eq!(
+            render(&snippet(text, &find(text, "本品"), 2)),
+            "[本品]には…"
+        );
+        assert_eq!(
+            render(&snippet(text, &find(text, "します。"), 3)),
+            "…やかに[します。]"
+        );
+        assert!(snippet(text, &[], 4).is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+//! 最近見た商品の履歴
+//!
+//! 商品一覧から詳細を開いた商品を新しい順に localStorage に保存する。
+
+use serde::{Deserialize, Serialize};
+
+use crate::Data;
+
+/** 保存する履歴の最大件数 */
+pub const MAX_ENTRIES: usize = 20;
+
+/** 履歴の 1 件 */
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ViewedProduct {
+    pub notificationNumber: String,
+    /** 見たときの商品名。データから商品が見つからなくなった場合の表示に使う */
+    pub productName: String,
+}
+
+/** 最近見た商品。新しい順に並べる */
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RecentlyViewed(pub Vec<ViewedProduct>);
+
+impl RecentlyViewed {
+    /// 商品を履歴の先頭に追加する。同じ商品がすでにある場合は先頭に移し、最大件数を超えた古い履歴は捨てる。
+    pub fn record(&mut self, item: &Data) {
+        self.0
+            .retain(|viewed| viewed.notificationNumber != item.notificationNumber);
+        self.0.insert(
+            0,
+            ViewedProduct {
+                notificationNumber: item.notificationNumber.clone(),
+                productName: item.productName.clone(),
+            },
+        );
+        self.0.truncate(MAX_ENTRIES);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(number: usize) -> Data {
+        Data::sample(&format!("A{}", number))
+    }
+
+    fn numbers(history: &RecentlyViewed) -> Vec<&str> {
+        history
+            .0
+            .iter()
+            .map(|viewed| viewed.notificationNumber.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn moves_revisited_products_to_the_front() {
+        let mut history = RecentlyViewed::default();
+        history.record(&data(1));
+        history.record(&data(2));
+        history.record(&data(1));
+        assert_eq!(numbers(&history), vec!["A1", "A2"]);
+    }
+
+    #[test]
+    fn keeps_at_most_max_entries() {
+        let mut history = RecentlyViewed::default();
+        for number in 0..MAX_ENTRIES + 5 {
+            history.record(&data(number));
+        }
+        assert_eq!(history.0.len(), MAX_ENTRIES);
+        assert_eq!(
+            history.0[0].notificationNumber,
+            format!("A{}", MAX_ENTRIES + 4)
+        );
+    }
+}
@@ -1,14 +1,38 @@
 #![allow(non_snake_case)]
 
+use std::ops::Range;
+
 use dioxus::prelude::*;
-use dioxus_free_icons::{icons::io_icons::{IoLogoTwitter, IoOpen, IoSearch}, Icon};
+use dioxus_free_icons::{icons::io_icons::{IoLogoTwitter, IoOpen, IoSearch, IoStar, IoStarOutline}, Icon};
 use log::LevelFilter;
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
 
+mod api;
+mod assessment;
+mod compare;
+mod concern;
+mod data_source;
+mod diff;
 mod env;
+mod favorites;
+mod highlight;
+mod history;
+mod normalize;
+mod notifier;
+mod retry;
+mod search;
+mod similar;
+mod snapshot;
+mod stats;
+mod storage;
+
+use assessment::{Assessment, Grade, GradeFilter};
+use compare::CompareSet;
+use data_source::DataSource;
+use search::{SearchField, SearchIndex, SearchMode, SearchQuery, SortOrder};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Data {
     /** 届出番号 */
     notificationNumber: String,
@@ -19,33 +43,115 @@ pub struct Data {
     /** 表示しようとする機能性 */
     functionalityToDisplay: String,
     /** ASCON 総合評価判定 */
-    assessment: String,
+    assessment: Assessment,
     /** 論文採用の根拠/機能性エビデンスの総評 */
     generalReviewOfEvidence: String,
+    /** 機能性関与成分名 (API v2 以降) */
+    #[serde(default)]
+    functionalIngredient: Option<String>,
+    /** 届出日 (API v2 以降) */
+    #[serde(default)]
+    notificationDate: Option<String>,
 }
 
+#[cfg(test)]
 impl Data {
-    fn new() -> Self {
-        Self {
-            notificationNumber: "".to_string(),
-            productName: "".to_string(),
-            notifierName: "".to_string(),
-            functionalityToDisplay: "".to_string(),
-            assessment: "".to_string(),
-            generalReviewOfEvidence: "".to_string(),
+    /// テスト用の商品。届出番号以外は固定の値にするので、必要な項目だけ上書きして使う。
+    pub fn sample(number: &str) -> Self {
+        Data {
+            notificationNumber: number.to_string(),
+            productName: format!("商品{}", number),
+            notifierName: "サンプル食品".to_string(),
+            functionalityToDisplay: "血圧".to_string(),
+            assessment: Assessment::A,
+            generalReviewOfEvidence: "総評".to_string(),
+            functionalIngredient: None,
+            notificationDate: None,
+        }
+    }
+}
+
+/** 取得したデータ。取得日時と合わせて localStorage にキャッシュする */
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Dataset {
+    items: Vec<Data>,
+    /** 取得日時 (UNIX 時間のミリ秒) */
+    fetchedAt: f64,
+    /** サーバーがデータを生成した日時 */
+    #[serde(default)]
+    generatedAt: Option<String>,
+    /** 読み取れずに読み飛ばしたレコード */
+    #[serde(default)]
+    skipped: Vec<api::SkippedRecord>,
+    /** データを取得した取得元の名前 */
+    #[serde(default)]
+    source: String,
+    /** 開発用のサンプルデータかどうか。本物のデータと混ざらないよう、キャッシュや差分には使わない */
+    #[serde(default)]
+    fromFixture: bool,
+}
+
+impl Dataset {
+    /// 取得元とその応答からデータを作る。取得日時は現在の日時にする。
+    fn new(source: &data_source::AnyDataSource, response: api::DataResponse) -> Self {
+        log::info!("schema version: {}, generated at: {:?}", response.schemaVersion, response.generatedAt);
+        Dataset {
+            items: response.records,
+            fetchedAt: js_sys::Date::now(),
+            generatedAt: response.generatedAt,
+            skipped: response.skipped,
+            source: source.name(),
+            fromFixture: matches!(source, data_source::AnyDataSource::Fixture(_)),
+        }
+    }
+
+    /// サーバーがデータを生成した日時を比べ、`other` より新しいデータかどうかを返す。
+    ///
+    /// どちらかの生成日時がわからない場合は新しいデータとして扱う。
+    fn is_newer_than(&self, other: &Dataset) -> bool {
+        let parse = |generated_at: &Option<String>| generated_at.as_deref().map(js_sys::Date::parse).filter(|time| !time.is_nan());
+        match (parse(&self.generatedAt), parse(&other.generatedAt)) {
+            (Some(new), Some(old)) => new > old,
+            _ => true,
         }
     }
 }
 
+/** 表示するデータの状態 */
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataState {
+    /** 表示するデータ。取得中でキャッシュもない場合は `None` */
+    dataset: Option<Dataset>,
+    /** 表示中のデータがキャッシュかどうか */
+    fromCache: bool,
+    /** 取得に失敗した場合に利用者に表示するメッセージ */
+    error: Option<String>,
+}
+
+/** 表示中のデータより新しいデータ。利用者が反映するまで保留する */
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingUpdate {
+    dataset: Dataset,
+    summary: diff::UpdateSummary,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppState {
     searchInput: String,
+    searchMode: SearchMode,
+    gradeFilter: GradeFilter,
+    /** 絞り込む健康の悩みの識別子。空文字列はすべての悩み */
+    concernFilter: String,
+    sortOrder: SortOrder,
+    /** お気に入りの商品だけを表示するかどうか。お気に入りは端末ごとに異なるため URL には含めない */
+    favoritesOnly: bool,
+    /** 比較する商品 */
+    compareSet: CompareSet,
     topNavbarBurgerActive: bool,
     topNavbarBurgerClass: String,
     topNavbarMenuClass: String,
-    detailModalActive: bool,
-    detailModalClass: String,
-    detailModalData: Data,
+    /** URL を持つモーダルをアプリ内から開いたかどうか。閉じるときに履歴を戻るかどうかの判定に使う */
+    modalOpenedFromList: bool,
     aboutModalActive: bool,
     aboutModalClass: String,
     termsOfUseModalActive: bool,
@@ -58,12 +164,16 @@ impl AppState {
     fn new() -> Self {
         Self {
             searchInput: "".to_string(),
+            searchMode: SearchMode::ProductName,
+            gradeFilter: GradeFilter::default(),
+            concernFilter: "".to_string(),
+            sortOrder: SortOrder::Relevance,
+            favoritesOnly: false,
+            compareSet: CompareSet::default(),
             topNavbarBurgerActive: false,
             topNavbarBurgerClass: "navbar-burger".to_string(),
             topNavbarMenuClass: "navbar-menu".to_string(),
-            detailModalActive: false,
-            detailModalClass: "modal".to_string(),
-            detailModalData: Data::new(),
+            modalOpenedFromList: false,
             aboutModalActive: false,
             aboutModalClass: "modal".to_string(),
             termsOfUseModalActive: false,
@@ -82,22 +192,225 @@ fn main() {
     launch(App);
 }
 
+#[derive(Routable, Clone, PartialEq)]
+#[rustfmt::skip]
+enum Route {
+    #[layout(Layout)]
+        #[route("/?:q&:grade&:concern&:mode&:sort")]
+        Home { q: SearchQuery, grade: GradeFilter, concern: String, mode: SearchMode, sort: SortOrder },
+        #[route("/product/:notificationNumber")]
+        Product { notificationNumber: String },
+        #[route("/compare?:items")]
+        Compare { items: CompareSet },
+        #[route("/notifier/:name")]
+        Notifier { name: String },
+        #[route("/whats-new")]
+        WhatsNew {},
+        #[route("/stats")]
+        Stats {},
+        #[route("/:..segments")]
+        PageNotFound { segments: Vec<String> },
+}
+
+impl Route {
+    /** 検索条件を指定しないトップページ */
+    fn home() -> Self {
+        Route::Home {
+            q: SearchQuery::default(),
+            grade: GradeFilter::default(),
+            concern: String::new(),
+            mode: SearchMode::default(),
+            sort: SortOrder::default(),
+        }
+    }
+}
+
 #[component]
 fn App() -> Element {
     use_context_provider(|| Signal::new(AppState::new()));
-    let app_state = consume_context::<Signal<AppState>>();
-    let data_resource = use_resource(move || get_data());
+    use_context_provider(|| {
+        Signal::new(storage::load::<favorites::Favorites>(storage::FAVORITES_KEY).unwrap_or_default())
+    });
+    use_context_provider(|| {
+        Signal::new(storage::load::<history::RecentlyViewed>(storage::RECENTLY_VIEWED_KEY).unwrap_or_default())
+    });
+    let data_resource = use_resource(load_dataset);
+    use_context_provider(|| data_resource);
+    // キャッシュを先に表示し、取得が終わったら差し替える
+    let cached_dataset = use_hook(|| storage::load::<Dataset>(&storage::data_cache_key()));
+    // 表示中に取得した新しいデータ。反映すると取得結果より優先して表示する
+    let mut applied_dataset = use_context_provider(|| Signal::new(None::<Dataset>));
+    let mut pending_update = use_context_provider(|| Signal::new(None::<PendingUpdate>));
+    let data_state = use_memo(move || match (&*applied_dataset.read(), &*data_resource.read()) {
+        (Some(dataset), _) => DataState {
+            dataset: Some(dataset.clone()),
+            fromCache: false,
+            error: None,
+        },
+        // どの取得元からも取得できずにサンプルデータになった場合は、キャッシュがあればそちらを表示する
+        (None, Some(Ok(dataset))) if dataset.fromFixture && cached_dataset.is_some() => DataState {
+            dataset: cached_dataset.clone(),
+            fromCache: true,
+            error: Some("データの取得に失敗しました。時間をおいて再試行してください。".to_string()),
+        },
+        (None, Some(Ok(dataset))) => DataState {
+            dataset: Some(dataset.clone()),
+            fromCache: false,
+            error: None,
+        },
+        (None, Some(Err(err))) => {
+            log::error!("Error: {:?}", err);
+            let message = if err.downcast_ref::<api::UnsupportedVersion>().is_some() {
+                "新しいバージョンのアプリが公開されています。ページを再読み込みしてください。"
+            } else {
+                "データの取得に失敗しました。時間をおいて再試行してください。"
+            };
+            DataState {
+                dataset: cached_dataset.clone(),
+                fromCache: true,
+                error: Some(message.to_string()),
+            }
+        }
+        (None, None) => DataState {
+            dataset: cached_dataset.clone(),
+            fromCache: true,
+            error: None,
+        },
+    });
+    use_context_provider(|| data_state);
+    // 前回の訪問から追加・変更・削除された商品
+    let mut visit_diff = use_context_provider(|| Signal::new(None::<diff::VisitDiff>));
+    use_effect(move || {
+        let data_state = data_state.read();
+        // キャッシュやサンプルデータ、代わりの取得元から取得した古いかもしれないデータは前回の訪問の記録と比べない
+        let primary = data_source::primary().name();
+        let Some(dataset) = data_state.dataset.as_ref().filter(|dataset| !data_state.fromCache && !dataset.fromFixture && dataset.source == primary) else {
+            return;
+        };
+        let mut history = storage::load::<diff::VisitHistory>(storage::VISIT_HISTORY_KEY).unwrap_or_default();
+        let diff = history.record(&dataset.items, dataset.fetchedAt);
+        storage::save(storage::VISIT_HISTORY_KEY, &history);
+        visit_diff.set(diff);
+    });
+    // 表示中のデータが最新かどうかを定期的に確かめる。キャッシュを表示しているオフラインの間も確かめ続ける
+    // 古いスナップショットなどを新しいデータと取り違えないよう、最初の取得元だけを確かめる
+    use_future(move || async move {
+        let interval = retry::parse_config(
+            "APP_REFRESH_INTERVAL_MS",
+            env::APP_REFRESH_INTERVAL_MS,
+            30 * 60 * 1000,
+        );
+        loop {
+            gloo_timers::future::TimeoutFuture::new(interval).await;
+            let Some(current) = data_state.peek().dataset.clone() else {
+                continue;
+            };
+            let from_cache = data_state.peek().fromCache;
+            let dataset = match fetch_primary_dataset().await {
+                Ok(dataset) if dataset.fromFixture => continue,
+                Ok(dataset) => dataset,
+                Err(err) => {
+                    log::warn!("Failed to check for updates: {:?}", err);
+                    continue;
+                }
+            };
+            // サンプルデータとは比べず、取得できたデータをそのまま表示する
+            if current.fromFixture {
+                cache_dataset(&dataset);
+                applied_dataset.set(Some(dataset));
+                continue;
+            }
+            let summary = diff::summarize(&current.items, &dataset.items);
+            log::info!("check for updates: {:?}", summary);
+            if summary.is_empty() {
+                if from_cache {
+                    // 内容は同じなので、取得に失敗したという表示だけを消す
+                    cache_dataset(&dataset);
+                    applied_dataset.set(Some(dataset));
+                }
+            } else if dataset.is_newer_than(&current) {
+                pending_update.set(Some(PendingUpdate { dataset, summary }));
+            } else {
+                log::info!("skip update generated at {:?}, not newer than {:?}", dataset.generatedAt, current.generatedAt);
+            }
+        }
+    });
+
+    rsx! {
+        Router::<Route> {}
+    }
+}
+
+#[component]
+fn Layout() -> Element {
+    let mut app_state = consume_context::<Signal<AppState>>();
+    // ブラウザの戻るでモーダルを閉じた場合も、トップページに戻ったら開いた記録を消す
+    let route = use_route::<Route>();
+    use_effect(use_reactive(&route, move |route| {
+        if matches!(route, Route::Home { .. }) && app_state.peek().modalOpenedFromList {
+            app_state.write().modalOpenedFromList = false;
+        }
+    }));
+    let data_state = consume_context::<Memo<DataState>>();
+    let favorites = consume_context::<Signal<favorites::Favorites>>();
+    let search_index = use_memo(move || match &data_state.read().dataset {
+        Some(dataset) => {
+            log::info!("get data: {} items", dataset.items.len());
+            SearchIndex::new(&dataset.items)
+        }
+        None => SearchIndex::default(),
+    });
+    let concern_index = use_memo(move || match &data_state.read().dataset {
+        Some(dataset) => concern::ConcernIndex::new(&dataset.items),
+        None => concern::ConcernIndex::default(),
+    });
+    let similarity_index = use_memo(move || match &data_state.read().dataset {
+        Some(dataset) => similar::SimilarityIndex::new(&dataset.items),
+        None => similar::SimilarityIndex::default(),
+    });
+    use_context_provider(|| search_index);
+    use_context_provider(|| concern_index);
+    use_context_provider(|| similarity_index);
 
     rsx! {
-        link { rel: "stylesheet", href: "styles/main.css" }
+        link { rel: "stylesheet", href: "{env::APP_BASE_PATH}/styles/main.css" }
         GoogleAnalytics {}
         Header {}
         div { class: "container p-3",
-            match &*data_resource.read_unchecked() {
-                Some(Ok(items)) => {
-                    log::info!("get data: {:?}", items);
-                    let filtered_items = items.iter().filter(|item| item.productName.contains(&app_state.read().searchInput)).collect::<Vec<&Data>>();
+            DataNotice {}
+            UpdateBanner {}
+            DiagnosticsPanel {}
+            match &data_state.read_unchecked().dataset {
+                Some(dataset) => {
+                    let items = &dataset.items;
+                    let search_mode = app_state.read().searchMode;
+                    let favorites_only = app_state.read().favoritesOnly;
+                    let hits = search::search(items, &search_index.read(), &app_state.read().searchInput, search_mode)
+                        .into_iter()
+                        .filter(|hit| !favorites_only || favorites.read().contains(&hit.item.notificationNumber))
+                        .collect::<Vec<_>>();
+                    let grade_filter = app_state.read().gradeFilter.clone();
+                    let concern_filter = concern::find(&app_state.read().concernFilter);
+                    let concern_index = concern_index.read();
+                    let concern_counts = concern::dictionary()
+                        .iter()
+                        .map(|concern| (concern, hits.iter().filter(|hit| grade_filter.matches(hit.item.assessment.grade()) && concern_index.matches(&hit.item.notificationNumber, Some(concern))).count()))
+                        .collect::<Vec<_>>();
+                    let hits = hits.into_iter().filter(|hit| concern_index.matches(&hit.item.notificationNumber, concern_filter)).collect::<Vec<_>>();
+                    let grade_counts = Grade::ALL.map(|grade| (grade, hits.iter().filter(|hit| hit.item.assessment.grade() == grade).count()));
+                    let mut filtered_items = hits.into_iter().filter(|hit| grade_filter.matches(hit.item.assessment.grade())).collect::<Vec<_>>();
+                    search::sort_hits(&mut filtered_items, app_state.read().sortOrder);
                     rsx! {
+                        if app_state.read().searchInput.is_empty() {
+                            RecentlyViewedSection {}
+                        }
+                        ConcernChips { counts: concern_counts }
+                        GradeFacets { counts: grade_counts.to_vec() }
+                        FavoritesFilter {}
+                        if favorites_only {
+                            FavoritesPanel {}
+                        }
+                        SortOrderSelect {}
                         div {
                             class: "table-container",
                             table {
@@ -108,13 +421,18 @@ fn App() -> Element {
                                     }
                                 }
                                 tbody {
-                                    if filtered_items.len() == 0 {
+                                    if filtered_items.is_empty() {
                                         tr {
                                             td { "該当する商品がありません。" }
                                         }
                                     } else {
-                                        for item in filtered_items {
-                                            TableRow {item: item.clone()}
+                                        for hit in filtered_items {
+                                            TableRow {
+                                                position: hit.position,
+                                                field: hit.field,
+                                                matches: hit.matches,
+                                                show_field: search_mode == SearchMode::AllFields,
+                                            }
                                         }
                                     }
                                 }
@@ -122,148 +440,1380 @@ fn App() -> Element {
                         }
                     }
                 }
-                Some(Err(err)) => {
-                    log::error!("Error: {:?}", err);
-                    rsx! {
-                        article {
-                            class: "message is-danger",
-                            div {
-                                class: "message-body",
-                                "データの取得に失敗しました。再読み込みをお試し下さい。"
-                            }
-                        }
-                    }
+                None if data_state.read().error.is_some() => {
+                    rsx! {
+                        article {
+                            class: "message is-danger",
+                            div {
+                                class: "message-body",
+                                p { "{data_state.read().error.clone().unwrap_or_default()}" }
+                                RetryButton {}
+                            }
+                        }
+                    }
+                }
+                None => {
+                    rsx! {Loading {}}
+                }
+            }
+            CompareTray {}
+        }
+        Outlet::<Route> {}
+        AboutModal {}
+        TermsOfUseModal {}
+        PrivacyPolicyModal {}
+    }
+}
+
+/// URL のクエリ文字列の検索条件を `AppState` に反映する。
+#[component]
+fn Home(q: SearchQuery, grade: GradeFilter, concern: String, mode: SearchMode, sort: SortOrder) -> Element {
+    let mut app_state = consume_context::<Signal<AppState>>();
+
+    use_effect(use_reactive((&q.0, &grade, &concern, &mode, &sort), move |(q, grade, concern, mode, sort)| {
+        let state = app_state.peek();
+        let changed = state.searchInput != q || state.gradeFilter != grade || state.concernFilter != concern || state.searchMode != mode || state.sortOrder != sort;
+        drop(state);
+        if changed {
+            let mut state = app_state.write();
+            state.searchInput = q;
+            state.gradeFilter = grade;
+            state.concernFilter = concern;
+            state.searchMode = mode;
+            state.sortOrder = sort;
+        }
+    }));
+    rsx! {}
+}
+
+/// `AppState` の検索条件を URL のクエリ文字列に反映する。
+///
+/// 入力中の検索語は 1 文字ごとに履歴が増えないよう `replace` で、それ以外の条件は `push` で反映する。
+/// データは `App` で取得しているため、URL が変わっても再取得はしない。
+fn sync_search_params(app_state: Signal<AppState>, replace: bool) {
+    let route = search_route(&app_state.read());
+    if replace {
+        navigator().replace(route);
+    } else {
+        navigator().push(route);
+    }
+}
+
+/// `AppState` の検索条件を表すトップページの URL。
+fn search_route(state: &AppState) -> Route {
+    Route::Home {
+        q: SearchQuery(state.searchInput.clone()),
+        grade: state.gradeFilter.clone(),
+        concern: state.concernFilter.clone(),
+        mode: state.searchMode,
+        sort: state.sortOrder,
+    }
+}
+
+/// URL のクエリ文字列の比較する商品を `AppState` に反映し、比較を表示する。
+#[component]
+fn Compare(items: CompareSet) -> Element {
+    let mut app_state = consume_context::<Signal<AppState>>();
+    let data_state = consume_context::<Memo<DataState>>();
+
+    use_effect(use_reactive(&items, move |items| {
+        if app_state.peek().compareSet != items {
+            app_state.write().compareSet = items;
+        }
+    }));
+
+    let columns = match &data_state.read().dataset {
+        Some(dataset) => items
+            .0
+            .iter()
+            .map(|number| {
+                let item = dataset.items.iter().find(|item| &item.notificationNumber == number).cloned();
+                (number.clone(), item)
+            })
+            .collect::<Vec<(String, Option<Data>)>>(),
+        None => return rsx! {},
+    };
+    rsx! {
+        div { id: "compare-modal", class: "modal is-active",
+            div { class: "modal-background" }
+            div { class: "modal-card compare-modal-card p-4",
+                header { class: "modal-card-head",
+                    p { class: "modal-card-title", "商品の比較" }
+                    button {
+                        class: "modal-close is-large",
+                        "aria-label": "close",
+                        onclick: move |_| close_modal(app_state)
+                    }
+                }
+                section { class: "modal-card-body",
+                    if columns.is_empty() {
+                        p { "比較する商品がありません。商品一覧の「比較」から商品を追加してください。" }
+                    } else {
+                        CompareTable { columns }
+                    }
+                }
+                footer { class: "modal-card-foot",
+                    div { class: "buttons",
+                        button {
+                            class: "button",
+                            onclick: move |_| close_modal(app_state),
+                            "閉じる"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 商品ごとの列に項目を揃えて並べ、商品によって値が異なる項目を強調する。
+#[component]
+fn CompareTable(columns: Vec<(String, Option<Data>)>) -> Element {
+    let mut app_state = consume_context::<Signal<AppState>>();
+
+    let items = columns.iter().map(|(_, item)| item.clone()).collect::<Vec<Option<Data>>>();
+    let rows = compare::CompareField::ALL.map(|field| {
+        let values = items
+            .iter()
+            .map(|item| item.as_ref().and_then(|item| field.value(item)))
+            .collect::<Vec<Option<String>>>();
+        (field, compare::differs(&values), values)
+    });
+    rsx! {
+        p { class: "help mb-2",
+            span { class: "has-background-warning-light px-1", "色付きの項目" }
+            "は商品によって内容が異なります。"
+        }
+        div { class: "table-container",
+            table { class: "table is-bordered is-fullwidth compare-table",
+                thead {
+                    tr {
+                        th { "届出番号" }
+                        for (number, item) in columns.iter().cloned() {
+                            th {
+                                "{number}"
+                                if item.is_none() {
+                                    span { class: "tag is-warning ml-2", "見つかりません" }
+                                }
+                                button {
+                                    class: "delete is-small ml-2",
+                                    "aria-label": "比較から外す",
+                                    title: "比較から外す",
+                                    onclick: move |_| {
+                                        app_state.write().compareSet.remove(&number);
+                                        let items = app_state.read().compareSet.clone();
+                                        navigator().replace(Route::Compare { items });
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                tbody {
+                    for (field, differs, values) in rows {
+                        tr { class: if differs { "has-background-warning-light" },
+                            th { "{field.label()}" }
+                            for (item, value) in items.iter().zip(values) {
+                                td {
+                                    match (field, item) {
+                                        (compare::CompareField::Search(SearchField::Assessment), Some(item)) => rsx! {
+                                            AssessmentTag { assessment: item.assessment.clone() }
+                                        },
+                                        _ => rsx! { "{value.unwrap_or_default()}" },
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 届出者 (会社) の商品と評価の分布を表示する。
+#[component]
+fn Notifier(name: String) -> Element {
+    let app_state = consume_context::<Signal<AppState>>();
+    let data_state = consume_context::<Memo<DataState>>();
+
+    let products = match &data_state.read().dataset {
+        Some(dataset) => notifier::products_of(&dataset.items, &notifier::notifier_key(&name)).into_iter().cloned().collect::<Vec<Data>>(),
+        None => return rsx! {},
+    };
+    let product_refs = products.iter().collect::<Vec<&Data>>();
+    let display_name = notifier::display_name(&product_refs).unwrap_or(name);
+    let distribution = notifier::grade_distribution(&product_refs);
+    let total = products.len();
+    rsx! {
+        div { id: "notifier-modal", class: "modal is-active",
+            div { class: "modal-background" }
+            div { class: "modal-card p-4",
+                header { class: "modal-card-head",
+                    p { class: "modal-card-title", "{display_name}" }
+                    button {
+                        class: "modal-close is-large",
+                        "aria-label": "close",
+                        onclick: move |_| close_modal(app_state)
+                    }
+                }
+                section { class: "modal-card-body",
+                    if products.is_empty() {
+                        p { "該当する届出者の商品がありません。" }
+                    } else {
+                        h3 { class: "title is-6", "ASCON 総合評価判定の分布 (全 {total} 件)" }
+                        table { class: "table is-narrow is-fullwidth",
+                            tbody {
+                                for (grade, count) in distribution.into_iter().filter(|(grade, count)| *grade != Grade::Other || *count > 0) {
+                                    tr {
+                                        td { class: "is-narrow",
+                                            span { class: "tag {grade.tag_class()}", "{grade.label()}" }
+                                        }
+                                        td { class: "is-vcentered",
+                                            progress {
+                                                class: "progress is-small {grade.tag_class()}",
+                                                value: "{count}",
+                                                max: "{total}",
+                                                "{count}"
+                                            }
+                                        }
+                                        td { class: "is-narrow has-text-right", "{count} 件" }
+                                    }
+                                }
+                            }
+                        }
+                        h3 { class: "title is-6", "商品一覧" }
+                        ul {
+                            for item in products.into_iter() {
+                                li { class: "mb-1",
+                                    AssessmentTag { assessment: item.assessment.clone(), class: "mr-2" }
+                                    a {
+                                        onclick: move |_| open_modal(app_state, Route::Product { notificationNumber: item.notificationNumber.clone() }),
+                                        "{item.productName}"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                footer { class: "modal-card-foot",
+                    div { class: "buttons",
+                        button {
+                            class: "button",
+                            onclick: move |_| close_modal(app_state),
+                            "閉じる"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn Product(notificationNumber: String) -> Element {
+    let data_state = consume_context::<Memo<DataState>>();
+
+    let item = match &data_state.read().dataset {
+        Some(dataset) => dataset.items.iter().find(|item| item.notificationNumber == notificationNumber).cloned(),
+        None => return rsx! {},
+    };
+    rsx! {
+        DetailModal { item }
+    }
+}
+
+/// 前回の訪問から追加・変更・削除された商品を表示する。
+#[component]
+fn WhatsNew() -> Element {
+    let app_state = consume_context::<Signal<AppState>>();
+    let visit_diff = consume_context::<Signal<Option<diff::VisitDiff>>>();
+
+    rsx! {
+        div { id: "whats-new-modal", class: "modal is-active",
+            div { class: "modal-background" }
+            div { class: "modal-card p-4",
+                header { class: "modal-card-head",
+                    p { class: "modal-card-title", "新着・変更" }
+                    button {
+                        class: "modal-close is-large",
+                        "aria-label": "close",
+                        onclick: move |_| close_modal(app_state)
+                    }
+                }
+                section { class: "modal-card-body",
+                    match &*visit_diff.read() {
+                        Some(diff) => rsx! { VisitDiffContent { diff: diff.clone() } },
+                        None => rsx! {
+                            p { "前回の訪問の記録がありません。次回以降の訪問で、今回から追加・変更された商品を表示します。" }
+                        },
+                    }
+                }
+                footer { class: "modal-card-foot",
+                    div { class: "buttons",
+                        button {
+                            class: "button",
+                            onclick: move |_| close_modal(app_state),
+                            "閉じる"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn VisitDiffContent(diff: diff::VisitDiff) -> Element {
+    let app_state = consume_context::<Signal<AppState>>();
+
+    if diff.is_empty() {
+        return rsx! {
+            p { "前回の訪問から変更はありません。" }
+        };
+    }
+    rsx! {
+        div { class: "content",
+            p { "{format_timestamp(diff.since)} に取得したデータからの変更です。" }
+            if !diff.added.is_empty() {
+                h3 { "追加 {diff.added.len()} 件" }
+                ul {
+                    for item in diff.added.into_iter() {
+                        li {
+                            AssessmentTag { assessment: item.assessment.clone(), class: "mr-2" }
+                            a {
+                                onclick: move |_| open_modal(app_state, Route::Product { notificationNumber: item.notificationNumber.clone() }),
+                                "{item.productName}"
+                            }
+                        }
+                    }
+                }
+            }
+            if !diff.changed.is_empty() {
+                h3 { "変更 {diff.changed.len()} 件" }
+                ul {
+                    for record in diff.changed.into_iter() {
+                        li {
+                            AssessmentTag { assessment: record.item.assessment.clone(), class: "mr-2" }
+                            a {
+                                onclick: move |_| open_modal(app_state, Route::Product { notificationNumber: record.item.notificationNumber.clone() }),
+                                "{record.item.productName}"
+                            }
+                            ul {
+                                for change in record.changes.iter() {
+                                    li {
+                                        match (&change.before, &change.after) {
+                                            (Some(before), Some(after)) => rsx! { "{change.field.label()}: {before} → {after}" },
+                                            (None, Some(_)) => rsx! { "{change.field.label()}が変更されました" },
+                                            (_, None) => rsx! { "{change.field.label()}が削除されました" },
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            if !diff.removed.is_empty() {
+                h3 { "削除 {diff.removed.len()} 件" }
+                ul {
+                    for record in diff.removed.iter() {
+                        li { "{record.productName}（届出番号 {record.notificationNumber}、判定 {record.assessment}）" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/** 横棒グラフの棒 */
+#[derive(Clone, PartialEq)]
+struct ChartBar {
+    label: String,
+    value: usize,
+    color: String,
+    /** 棒を押したときに開くモーダル */
+    route: Option<Route>,
+}
+
+/** 横棒グラフの 1 行の高さ */
+const CHART_ROW_HEIGHT: f64 = 28.0;
+/** グラフの幅 (viewBox の単位) */
+const CHART_WIDTH: f64 = 640.0;
+/** 横棒グラフのラベルの幅 */
+const CHART_LABEL_WIDTH: f64 = 200.0;
+/** 横棒グラフの棒の最大の長さ。残りは件数の表示に使う */
+const CHART_BAR_WIDTH: f64 = 360.0;
+
+/// グラフのラベルを長さで切り詰める。
+fn truncate_label(label: &str, max_chars: usize) -> String {
+    if label.chars().count() <= max_chars {
+        label.to_string()
+    } else {
+        format!("{}…", label.chars().take(max_chars - 1).collect::<String>())
+    }
+}
+
+/// データ全体の統計をグラフで表示する。
+#[component]
+fn Stats() -> Element {
+    let app_state = consume_context::<Signal<AppState>>();
+    let data_state = consume_context::<Memo<DataState>>();
+    let concern_index = consume_context::<Memo<concern::ConcernIndex>>();
+
+    let (statistics, fetched_at) = match &data_state.read().dataset {
+        Some(dataset) => (stats::Statistics::new(&dataset.items, &concern_index.read()), format_timestamp(dataset.fetchedAt)),
+        None => return rsx! {},
+    };
+    let grade_bars = statistics
+        .grades
+        .iter()
+        .filter(|(grade, count)| *grade != Grade::Other || *count > 0)
+        .map(|(grade, count)| ChartBar { label: grade.label().to_string(), value: *count, color: stats::grade_color(*grade).to_string(), route: None })
+        .collect::<Vec<ChartBar>>();
+    let notifier_bars = statistics
+        .topNotifiers
+        .iter()
+        .map(|notifier| ChartBar {
+            label: truncate_label(&notifier.name, 14),
+            value: notifier.count,
+            color: "#485fc7".to_string(),
+            route: Some(Route::Notifier { name: notifier.key.clone() }),
+        })
+        .collect::<Vec<ChartBar>>();
+    rsx! {
+        div { id: "stats-modal", class: "modal is-active",
+            div { class: "modal-background" }
+            div { class: "modal-card p-4",
+                header { class: "modal-card-head",
+                    p { class: "modal-card-title", "統計" }
+                    button {
+                        class: "modal-close is-large",
+                        "aria-label": "close",
+                        onclick: move |_| close_modal(app_state)
+                    }
+                }
+                section { class: "modal-card-body",
+                    p { class: "mb-4", "全 {statistics.total} 件 (データの取得日時: {fetched_at})" }
+                    h3 { class: "title is-6", "ASCON 総合評価判定の分布" }
+                    BarChart { title: "ASCON 総合評価判定の分布", bars: grade_bars }
+                    h3 { class: "title is-6 mt-5", "商品数の多い届出者 (上位 {stats::TOP_NOTIFIERS} 社)" }
+                    BarChart { title: "商品数の多い届出者", bars: notifier_bars }
+                    h3 { class: "title is-6 mt-5", "健康の悩みごとの評価" }
+                    GradeStackedChart { rows: statistics.concernGrades.iter().map(|row| (row.concern.label.clone(), row.grades.clone())).collect::<Vec<_>>() }
+                    h3 { class: "title is-6 mt-5", "届出番号の系列ごとの件数" }
+                    SeriesChart { series: statistics.series.clone() }
+                    if statistics.unknownSeries > 0 {
+                        p { class: "is-size-7", "届出番号から系列を読み取れなかった {statistics.unknownSeries} 件を除きます。" }
+                    }
+                }
+                footer { class: "modal-card-foot",
+                    div { class: "buttons",
+                        button {
+                            class: "button",
+                            onclick: move |_| close_modal(app_state),
+                            "閉じる"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 件数の横棒グラフ。
+#[component]
+fn BarChart(title: String, bars: Vec<ChartBar>) -> Element {
+    let app_state = consume_context::<Signal<AppState>>();
+
+    let max = bars.iter().map(|bar| bar.value).max().unwrap_or(0).max(1) as f64;
+    let height = CHART_ROW_HEIGHT * bars.len().max(1) as f64;
+    rsx! {
+        svg {
+            class: "chart",
+            role: "img",
+            "aria-label": "{title}",
+            view_box: "0 0 {CHART_WIDTH} {height}",
+            width: "100%",
+            for (index, bar) in bars.into_iter().enumerate() {
+                g {
+                    class: if bar.route.is_some() { "chart-link" },
+                    onclick: move |_| {
+                        if let Some(route) = bar.route.clone() {
+                            open_modal(app_state, route);
+                        }
+                    },
+                    text {
+                        x: "{CHART_LABEL_WIDTH - 8.0}",
+                        y: "{CHART_ROW_HEIGHT * index as f64 + CHART_ROW_HEIGHT / 2.0}",
+                        text_anchor: "end",
+                        dominant_baseline: "middle",
+                        font_size: "13",
+                        "{bar.label}"
+                    }
+                    rect {
+                        x: "{CHART_LABEL_WIDTH}",
+                        y: "{CHART_ROW_HEIGHT * index as f64 + 4.0}",
+                        width: "{CHART_BAR_WIDTH * bar.value as f64 / max}",
+                        height: "{CHART_ROW_HEIGHT - 8.0}",
+                        fill: "{bar.color}",
+                    }
+                    text {
+                        x: "{CHART_LABEL_WIDTH + CHART_BAR_WIDTH * bar.value as f64 / max + 6.0}",
+                        y: "{CHART_ROW_HEIGHT * index as f64 + CHART_ROW_HEIGHT / 2.0}",
+                        dominant_baseline: "middle",
+                        font_size: "13",
+                        "{bar.value} 件"
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 行ごとの評価の区分の件数を積み上げた横棒グラフ。
+#[component]
+fn GradeStackedChart(rows: Vec<(String, Vec<(Grade, usize)>)>) -> Element {
+    let max = rows.iter().map(|(_, grades)| grades.iter().map(|(_, count)| count).sum::<usize>()).max().unwrap_or(0).max(1) as f64;
+    let legend_height = CHART_ROW_HEIGHT;
+    let height = legend_height + CHART_ROW_HEIGHT * rows.len().max(1) as f64;
+    rsx! {
+        svg {
+            class: "chart",
+            role: "img",
+            "aria-label": "健康の悩みごとの評価",
+            view_box: "0 0 {CHART_WIDTH} {height}",
+            width: "100%",
+            for (index, grade) in Grade::ALL.into_iter().enumerate() {
+                rect {
+                    x: "{CHART_LABEL_WIDTH + 60.0 * index as f64}",
+                    y: "6",
+                    width: "14",
+                    height: "14",
+                    fill: "{stats::grade_color(grade)}",
+                }
+                text {
+                    x: "{CHART_LABEL_WIDTH + 60.0 * index as f64 + 18.0}",
+                    y: "13",
+                    dominant_baseline: "middle",
+                    font_size: "12",
+                    "{grade.label()}"
+                }
+            }
+            for (index, (label, grades)) in rows.into_iter().enumerate() {
+                {
+                    let y = legend_height + CHART_ROW_HEIGHT * index as f64;
+                    let total = grades.iter().map(|(_, count)| count).sum::<usize>();
+                    let mut offset = CHART_LABEL_WIDTH;
+                    let segments = grades
+                        .into_iter()
+                        .filter(|(_, count)| *count > 0)
+                        .map(|(grade, count)| {
+                            let width = CHART_BAR_WIDTH * count as f64 / max;
+                            let x = offset;
+                            offset += width;
+                            (grade, count, x, width)
+                        })
+                        .collect::<Vec<_>>();
+                    rsx! {
+                        g {
+                            text {
+                                x: "{CHART_LABEL_WIDTH - 8.0}",
+                                y: "{y + CHART_ROW_HEIGHT / 2.0}",
+                                text_anchor: "end",
+                                dominant_baseline: "middle",
+                                font_size: "13",
+                                "{label}"
+                            }
+                            for (grade, count, x, width) in segments {
+                                rect {
+                                    x: "{x}",
+                                    y: "{y + 4.0}",
+                                    width: "{width}",
+                                    height: "{CHART_ROW_HEIGHT - 8.0}",
+                                    fill: "{stats::grade_color(grade)}",
+                                }
+                                if width >= 20.0 {
+                                    text {
+                                        x: "{x + width / 2.0}",
+                                        y: "{y + CHART_ROW_HEIGHT / 2.0}",
+                                        text_anchor: "middle",
+                                        dominant_baseline: "middle",
+                                        font_size: "11",
+                                        "{count}"
+                                    }
+                                }
+                            }
+                            text {
+                                x: "{offset + 6.0}",
+                                y: "{y + CHART_ROW_HEIGHT / 2.0}",
+                                dominant_baseline: "middle",
+                                font_size: "13",
+                                "{total} 件"
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 届出番号の系列ごとの件数の縦棒グラフと累計の折れ線グラフ。
+#[component]
+fn SeriesChart(series: Vec<stats::SeriesCount>) -> Element {
+    if series.is_empty() {
+        return rsx! {
+            p { "届出番号から系列を読み取れる商品がありません。" }
+        };
+    }
+    let height = 240.0;
+    let top = 24.0;
+    let bottom = height - 24.0;
+    let left = 40.0;
+    let step = (CHART_WIDTH - left * 2.0) / series.len() as f64;
+    let max_count = series.iter().map(|s| s.count).max().unwrap_or(0).max(1) as f64;
+    let max_cumulative = series.last().map(|s| s.cumulative).unwrap_or(0).max(1) as f64;
+    let cumulative_points = series
+        .iter()
+        .enumerate()
+        .map(|(index, s)| format!("{:.1},{:.1}", left + step * (index as f64 + 0.5), bottom - (bottom - top) * s.cumulative as f64 / max_cumulative))
+        .collect::<Vec<String>>()
+        .join(" ");
+    rsx! {
+        svg {
+            class: "chart",
+            role: "img",
+            "aria-label": "届出番号の系列ごとの件数",
+            view_box: "0 0 {CHART_WIDTH} {height}",
+            width: "100%",
+            line { x1: "{left}", y1: "{bottom}", x2: "{CHART_WIDTH - left}", y2: "{bottom}", stroke: "#b5b5b5" }
+            for (index, s) in series.into_iter().enumerate() {
+                {
+                    let bar_height = (bottom - top) * s.count as f64 / max_count;
+                    let x = left + step * index as f64;
+                    rsx! {
+                        g {
+                            rect {
+                                x: "{x + step * 0.2}",
+                                y: "{bottom - bar_height}",
+                                width: "{step * 0.6}",
+                                height: "{bar_height}",
+                                fill: "#485fc7",
+                            }
+                            text {
+                                x: "{x + step * 0.5}",
+                                y: "{bottom - bar_height - 4.0}",
+                                text_anchor: "middle",
+                                font_size: "11",
+                                "{s.count}"
+                            }
+                            text {
+                                x: "{x + step * 0.5}",
+                                y: "{bottom + 16.0}",
+                                text_anchor: "middle",
+                                font_size: "12",
+                                "{s.series}"
+                            }
+                        }
+                    }
+                }
+            }
+            polyline { points: "{cumulative_points}", fill: "none", stroke: "#f14668", stroke_width: "2" }
+            text { x: "{CHART_WIDTH - left}", y: "{top - 8.0}", text_anchor: "end", font_size: "12", fill: "#f14668", "累計 {max_cumulative} 件" }
+        }
+    }
+}
+
+#[component]
+fn PageNotFound(segments: Vec<String>) -> Element {
+    rsx! {
+        div { class: "container px-3",
+            article { class: "message is-warning",
+                div { class: "message-body",
+                    "ページが見つかりません。"
+                    Link { to: Route::home(), "トップページへ戻る" }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn GoogleAnalytics() -> Element {
+    rsx! {
+        script {
+            r#async: true,
+            src: "https://www.googletagmanager.com/gtag/js?id={env::APP_GA_TRACKING_ID}"
+        }
+        script {
+            r#"
+            window.dataLayer = window.dataLayer || [];
+            function gtag(){{dataLayer.push(arguments);}}
+            gtag('js', new Date());
+            gtag('config', '{env::APP_GA_TRACKING_ID}');
+            "#
+        }
+    }
+}
+
+#[component]
+fn Header() -> Element {
+    let mut app_state = consume_context::<Signal<AppState>>();
+    let visit_diff = consume_context::<Signal<Option<diff::VisitDiff>>>();
+    let changes = visit_diff.read().as_ref().map_or(0, diff::VisitDiff::len);
+
+    rsx! {
+        header {
+            nav { id: "top-navbar", class: "navbar",
+                div { class: "navbar-brand",
+                    Link { to: Route::home(), class: "navbar-item header-title", "アンシンめし" }
+                    a {
+                        id: "top-navbar-burger",
+                        class: "{app_state.read().topNavbarBurgerClass}",
+                        "aria-expanded": "false",
+                        "data-target": "top-navbar-menu",
+                        onclick: move |_| {
+                            if app_state.read().topNavbarBurgerActive {
+                                app_state.write().topNavbarBurgerActive = false;
+                                app_state.write().topNavbarBurgerClass = "navbar-burger".to_string();
+                                app_state.write().topNavbarMenuClass = "navbar-menu".to_string();
+                            } else {
+                                app_state.write().topNavbarBurgerActive = true;
+                                app_state
+                                    .write()
+                                    .topNavbarBurgerClass = "navbar-burger is-active".to_string();
+                                app_state.write().topNavbarMenuClass = "navbar-menu is-active".to_string();
+                            }
+                        },
+                        span { "aria-hidden": "true" }
+                        span { "aria-hidden": "true" }
+                        span { "aria-hidden": "true" }
+                        span { "aria-hidden": "true" }
+                    }
+                }
+                div {
+                    id: "top-navbar-menu",
+                    class: "{app_state.read().topNavbarMenuClass}",
+                    div { class: "navbar-start",
+                        a {
+                            class: "navbar-item",
+                            onclick: move |_| open_modal(app_state, Route::WhatsNew {}),
+                            "新着・変更"
+                            if changes > 0 {
+                                span { class: "tag is-info is-rounded ml-2", "{changes}" }
+                            }
+                        }
+                        a {
+                            class: "navbar-item",
+                            onclick: move |_| open_modal(app_state, Route::Stats {}),
+                            "統計"
+                        }
+                    }
+                    div { class: "navbar-end",
+                        div { class: "navbar-item",
+                            div { class: "buttons",
+                                a {
+                                    href: "#",
+                                    class: "Header-link",
+                                    "data-target": "about-modal",
+                                    onclick: move |_| {
+                                        if app_state.read().aboutModalActive {
+                                            app_state.write().aboutModalActive = false;
+                                            app_state.write().aboutModalClass = "modal".to_string();
+                                        } else {
+                                            app_state.write().aboutModalActive = true;
+                                            app_state.write().aboutModalClass = "modal is-active".to_string();
+                                        }
+                                    },
+                                    "このサイトについて"
+                                }
+                                a {
+                                    href: "#",
+                                    class: "Header-link",
+                                    "data-target": "terms-of-use-modal",
+                                    onclick: move |_| {
+                                        if app_state.read().termsOfUseModalActive {
+                                            app_state.write().termsOfUseModalActive = false;
+                                            app_state.write().termsOfUseModalClass = "modal".to_string();
+                                        } else {
+                                            app_state.write().termsOfUseModalActive = true;
+                                            app_state.write().termsOfUseModalClass = "modal is-active".to_string();
+                                        }
+                                    },
+                                    "利用規約"
+                                }
+                                a {
+                                    href: "#",
+                                    class: "Header-link",
+                                    "data-target": "privacy-policy-modal",
+                                    onclick: move |_| {
+                                        if app_state.read().privacyPolicyModalActive {
+                                            app_state.write().privacyPolicyModalActive = false;
+                                            app_state.write().privacyPolicyModalClass = "modal".to_string();
+                                        } else {
+                                            app_state.write().privacyPolicyModalActive = true;
+                                            app_state.write().privacyPolicyModalClass = "modal is-active".to_string();
+                                        }
+                                    },
+
+                                    "個人情報保護方針"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            div { class: "container px-3 pt-3",
+                div { class: "control has-icons-left",
+                    input {
+                        class: "input is-medium",
+                        r#type: "text",
+                        placeholder: if app_state.read().searchMode == SearchMode::AllFields { "商品名・届出者名・機能性などを入力してください" } else { "商品名を入力してください" },
+                        value: "{app_state.read().searchInput}",
+                        oninput: move |event| {
+                            app_state.write().searchInput = event.value();
+                            sync_search_params(app_state, true);
+                        }
+                    }
+                    span { class: "icon is-medium is-left", Icon { width: 24, height: 24, icon: IoSearch } }
+                }
+                div { class: "control mt-2",
+                    label { class: "radio",
+                        input {
+                            r#type: "radio",
+                            name: "search-mode",
+                            checked: app_state.read().searchMode == SearchMode::ProductName,
+                            onchange: move |_| {
+                                app_state.write().searchMode = SearchMode::ProductName;
+                                sync_search_params(app_state, false);
+                            }
+                        }
+                        " 商品名で検索"
+                    }
+                    label { class: "radio",
+                        input {
+                            r#type: "radio",
+                            name: "search-mode",
+                            checked: app_state.read().searchMode == SearchMode::AllFields,
+                            onchange: move |_| {
+                                app_state.write().searchMode = SearchMode::AllFields;
+                                sync_search_params(app_state, false);
+                            }
+                        }
+                        " すべての項目で検索"
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// キャッシュしたデータを表示している場合にその取得日時を、サンプルデータを表示している場合にそのことを知らせる。
+#[component]
+fn DataNotice() -> Element {
+    let data_state = consume_context::<Memo<DataState>>();
+    let data_state = data_state.read();
+
+    if data_state.dataset.as_ref().is_some_and(|dataset| dataset.fromFixture) {
+        return rsx! {
+            article { class: "message is-warning",
+                div { class: "message-body",
+                    p { "データの取得に失敗したため、開発用のサンプルデータを表示しています。" }
+                    RetryButton {}
+                }
+            }
+        };
+    }
+    let dataset = data_state.dataset.as_ref().filter(|_| data_state.fromCache)?;
+    let fetched_at = format_timestamp(dataset.fetchedAt);
+    rsx! {
+        if let Some(error) = &data_state.error {
+            article { class: "message is-warning",
+                div { class: "message-body",
+                    p { "{error} {fetched_at} に取得したデータを表示しています。" }
+                    RetryButton {}
+                }
+            }
+        } else {
+            p { class: "help mb-2", "{fetched_at} に取得したデータを表示しています。最新のデータを取得中です…" }
+        }
+    }
+}
+
+/// データの取得をやり直すボタン。ページ全体は再読み込みしない。
+#[component]
+fn RetryButton() -> Element {
+    let mut data_resource = consume_context::<Resource<Result<Dataset>>>();
+
+    rsx! {
+        button {
+            class: "button is-small mt-2",
+            onclick: move |_| data_resource.restart(),
+            "再試行"
+        }
+    }
+}
+
+/// 新しいデータがある場合に変更の件数を知らせ、検索条件や開いている詳細を保ったまま反映する。
+#[component]
+fn UpdateBanner() -> Element {
+    let mut pending_update = consume_context::<Signal<Option<PendingUpdate>>>();
+    let mut applied_dataset = consume_context::<Signal<Option<Dataset>>>();
+
+    let summary = pending_update.read().as_ref()?.summary;
+    rsx! {
+        div { class: "notification is-info is-light py-3",
+            button {
+                class: "delete",
+                "aria-label": "閉じる",
+                onclick: move |_| pending_update.set(None),
+            }
+            span { class: "mr-3",
+                "新しいデータがあります（追加 {summary.added} 件・更新 {summary.updated} 件"
+                if summary.removed > 0 {
+                    "・削除 {summary.removed} 件"
+                }
+                "）"
+            }
+            button {
+                class: "button is-small is-info",
+                onclick: move |_| {
+                    if let Some(update) = pending_update.take() {
+                        cache_dataset(&update.dataset);
+                        applied_dataset.set(Some(update.dataset));
+                    }
+                },
+                "反映する"
+            }
+        }
+    }
+}
+
+/// 読み取れずに読み飛ばしたレコードを折りたたみで表示する。
+#[component]
+fn DiagnosticsPanel() -> Element {
+    let data_state = consume_context::<Memo<DataState>>();
+    let data_state = data_state.read();
+
+    let skipped = &data_state.dataset.as_ref()?.skipped;
+    if skipped.is_empty() {
+        return rsx! {};
+    }
+    rsx! {
+        details { class: "notification is-warning is-light",
+            summary { "{skipped.len()} 件のデータを読み取れなかったため表示していません。" }
+            div { class: "table-container mt-2",
+                table { class: "table is-narrow is-fullwidth",
+                    thead {
+                        tr {
+                            th { "位置" }
+                            th { "届出番号" }
+                            th { "項目" }
+                            th { "内容" }
+                        }
+                    }
+                    tbody {
+                        for record in skipped.iter() {
+                            tr {
+                                td { "{record.index + 1}" }
+                                td { "{record.notificationNumber.clone().unwrap_or_default()}" }
+                                td { "{record.path}" }
+                                td { "{record.message}" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 健康の悩みで絞り込むチップ。商品のない悩みは選ばれている場合を除いて表示しない。
+#[component]
+fn ConcernChips(counts: Vec<(&'static concern::Concern, usize)>) -> Element {
+    let mut app_state = consume_context::<Signal<AppState>>();
+    let selected = app_state.read().concernFilter.clone();
+    let all_class = if concern::find(&selected).is_none() { "is-primary" } else { "is-light" };
+
+    rsx! {
+        div { class: "mb-3",
+            p { class: "mb-2", "健康の悩みから探す:" }
+            div { class: "tags",
+                a {
+                    class: "tag is-rounded {all_class}",
+                    onclick: move |_| {
+                        app_state.write().concernFilter = "".to_string();
+                        sync_search_params(app_state, false);
+                    },
+                    "すべて"
+                }
+                for (concern, count) in counts.into_iter().filter(|(concern, count)| *count > 0 || concern.id == selected) {
+                    a {
+                        class: if concern.id == selected { "tag is-rounded is-primary" } else { "tag is-rounded is-light" },
+                        onclick: move |_| {
+                            let mut state = app_state.write();
+                            state.concernFilter = if state.concernFilter == concern.id { "".to_string() } else { concern.id.clone() };
+                            drop(state);
+                            sync_search_params(app_state, false);
+                        },
+                        "{concern.label} ({count})"
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn GradeFacets(counts: Vec<(Grade, usize)>) -> Element {
+    let mut app_state = consume_context::<Signal<AppState>>();
+
+    rsx! {
+        div { class: "field is-grouped is-grouped-multiline",
+            span { class: "mr-3", "ASCON 総合評価判定で絞り込む:" }
+            for (grade, count) in counts.into_iter().filter(|(grade, count)| *grade != Grade::Other || *count > 0) {
+                label { class: "checkbox mr-3",
+                    input {
+                        r#type: "checkbox",
+                        checked: app_state.read().gradeFilter.0.contains(&grade),
+                        onchange: move |event| {
+                            if event.checked() {
+                                app_state.write().gradeFilter.0.insert(grade);
+                            } else {
+                                app_state.write().gradeFilter.0.remove(&grade);
+                            }
+                            sync_search_params(app_state, false);
+                        }
+                    }
+                    " {grade.label()} ({count})"
+                }
+            }
+        }
+    }
+}
+
+/// 詳細を開いた商品を最近見た商品の履歴に追加する。
+fn record_viewed(mut recently_viewed: Signal<history::RecentlyViewed>, item: &Data) {
+    recently_viewed.write().record(item);
+    storage::save(storage::RECENTLY_VIEWED_KEY, &*recently_viewed.read());
+}
+
+/// 最近見た商品。検索語が空のときに商品一覧の上に表示する。
+#[component]
+fn RecentlyViewedSection() -> Element {
+    let app_state = consume_context::<Signal<AppState>>();
+    let data_state = consume_context::<Memo<DataState>>();
+    let mut recently_viewed = consume_context::<Signal<history::RecentlyViewed>>();
+
+    if recently_viewed.read().0.is_empty() {
+        return rsx! {};
+    }
+    let entries = {
+        let data_state = data_state.read();
+        let items = &data_state.dataset.as_ref()?.items;
+        recently_viewed
+            .read()
+            .0
+            .iter()
+            .map(|viewed| {
+                let item = items.iter().find(|item| item.notificationNumber == viewed.notificationNumber).cloned();
+                (viewed.clone(), item)
+            })
+            .collect::<Vec<(history::ViewedProduct, Option<Data>)>>()
+    };
+    rsx! {
+        div { class: "box",
+            div { class: "level is-mobile mb-2",
+                div { class: "level-left",
+                    h2 { class: "level-item title is-6", "最近見た商品" }
+                }
+                div { class: "level-right",
+                    button {
+                        class: "level-item button is-small is-text",
+                        onclick: move |_| {
+                            recently_viewed.write().0.clear();
+                            storage::save(storage::RECENTLY_VIEWED_KEY, &*recently_viewed.read());
+                        },
+                        "履歴を消去"
+                    }
+                }
+            }
+            ul {
+                for (viewed, item) in entries {
+                    li { class: "mb-1",
+                        if let Some(item) = item {
+                            AssessmentTag { assessment: item.assessment.clone(), class: "mr-2" }
+                            a {
+                                onclick: move |_| {
+                                    record_viewed(recently_viewed, &item);
+                                    open_modal(app_state, Route::Product { notificationNumber: item.notificationNumber.clone() });
+                                },
+                                "{viewed.productName}"
+                            }
+                        } else {
+                            span { class: "tag is-warning mr-2", "見つかりません" }
+                            "{viewed.productName}"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 比較する商品に追加・削除するボタン。`label` が `true` の場合は大きなボタンにする。
+#[component]
+fn CompareButton(notificationNumber: String, label: bool) -> Element {
+    let mut app_state = consume_context::<Signal<AppState>>();
+
+    let in_compare_set = app_state.read().compareSet.contains(&notificationNumber);
+    let disabled = !in_compare_set && app_state.read().compareSet.is_full();
+    let title = if in_compare_set {
+        "比較から外す".to_string()
+    } else if disabled {
+        format!("比較できるのは {} 件までです", compare::MAX_ITEMS)
+    } else {
+        "比較に追加".to_string()
+    };
+    let class = match (label, in_compare_set) {
+        (true, true) => "button is-link",
+        (true, false) => "button is-link is-light",
+        (false, true) => "button is-link is-small mr-2",
+        (false, false) => "button is-link is-light is-small mr-2",
+    };
+    rsx! {
+        button {
+            class: "{class}",
+            title: "{title}",
+            "aria-pressed": "{in_compare_set}",
+            disabled,
+            onclick: move |event| {
+                // 行のクリックで詳細を開かないようにする
+                event.stop_propagation();
+                if in_compare_set {
+                    app_state.write().compareSet.remove(&notificationNumber);
+                } else {
+                    app_state.write().compareSet.add(&notificationNumber);
+                }
+            },
+            if label { "{title}" } else if in_compare_set { "比較中" } else { "比較" }
+        }
+    }
+}
+
+/// 比較する商品を商品一覧の下に表示し、比較を開く。
+#[component]
+fn CompareTray() -> Element {
+    let mut app_state = consume_context::<Signal<AppState>>();
+    let data_state = consume_context::<Memo<DataState>>();
+
+    let compare_set = app_state.read().compareSet.clone();
+    if compare_set.0.is_empty() {
+        return rsx! {};
+    }
+    let names = {
+        let data_state = data_state.read();
+        let items = &data_state.dataset.as_ref()?.items;
+        compare_set
+            .0
+            .iter()
+            .map(|number| {
+                let name = items
+                    .iter()
+                    .find(|item| &item.notificationNumber == number)
+                    .map_or_else(|| number.clone(), |item| item.productName.clone());
+                (number.clone(), name)
+            })
+            .collect::<Vec<(String, String)>>()
+    };
+    rsx! {
+        div { class: "box compare-tray",
+            div { class: "tags mb-2",
+                span { class: "mr-2", "比較 ({compare_set.0.len()}/{compare::MAX_ITEMS}):" }
+                for (number, name) in names {
+                    span { class: "tag is-link is-light",
+                        "{name}"
+                        button {
+                            class: "delete is-small",
+                            "aria-label": "比較から外す",
+                            onclick: move |_| app_state.write().compareSet.remove(&number),
+                        }
+                    }
+                }
+            }
+            div { class: "buttons",
+                button {
+                    class: "button is-link is-small",
+                    disabled: compare_set.0.len() < 2,
+                    onclick: move |_| open_modal(app_state, Route::Compare { items: compare_set.clone() }),
+                    "比較する"
                 }
-                None => {
-                    rsx! {Loading {}}
+                button {
+                    class: "button is-small",
+                    onclick: move |_| app_state.write().compareSet = CompareSet::default(),
+                    "クリア"
                 }
             }
         }
-        DetailModal {}
-        AboutModal {}
-        TermsOfUseModal {}
-        PrivacyPolicyModal {}
     }
 }
 
+/// お気に入りを切り替える星のボタン。`label` が `true` の場合は文言付きのボタンにする。
 #[component]
-fn GoogleAnalytics() -> Element {
+fn FavoriteButton(notificationNumber: String, label: bool) -> Element {
+    let mut favorites = consume_context::<Signal<favorites::Favorites>>();
+    let data_state = consume_context::<Memo<DataState>>();
+
+    let is_favorite = favorites.read().contains(&notificationNumber);
+    let title = if is_favorite { "お気に入りから外す" } else { "お気に入りに追加" };
+    let toggle = move |event: MouseEvent| {
+        // 行のクリックで詳細を開かないようにする
+        event.stop_propagation();
+        let data_state = data_state.peek();
+        let Some(item) = data_state.dataset.as_ref().and_then(|dataset| dataset.items.iter().find(|item| item.notificationNumber == notificationNumber)) else {
+            return;
+        };
+        favorites.write().toggle(item);
+        storage::save(storage::FAVORITES_KEY, &*favorites.read());
+    };
     rsx! {
-        script {
-            r#async: true,
-            src: "https://www.googletagmanager.com/gtag/js?id={env::APP_GA_TRACKING_ID}"
-        }
-        script {
-            r#"
-            window.dataLayer = window.dataLayer || [];
-            function gtag(){{dataLayer.push(arguments);}}
-            gtag('js', new Date());
-            gtag('config', '{env::APP_GA_TRACKING_ID}');
-            "#
+        if label {
+            button { class: "button is-warning is-light", onclick: toggle,
+                span { class: "icon",
+                    if is_favorite {
+                        Icon { width: 20, height: 20, icon: IoStar }
+                    } else {
+                        Icon { width: 20, height: 20, icon: IoStarOutline }
+                    }
+                }
+                span { "{title}" }
+            }
+        } else {
+            button {
+                class: "button is-white is-small mr-1",
+                title: "{title}",
+                "aria-label": "{title}",
+                "aria-pressed": "{is_favorite}",
+                onclick: toggle,
+                span { class: "icon has-text-warning",
+                    if is_favorite {
+                        Icon { width: 18, height: 18, icon: IoStar }
+                    } else {
+                        Icon { width: 18, height: 18, icon: IoStarOutline }
+                    }
+                }
+            }
         }
     }
 }
 
 #[component]
-fn Header() -> Element {
+fn FavoritesFilter() -> Element {
     let mut app_state = consume_context::<Signal<AppState>>();
+    let favorites = consume_context::<Signal<favorites::Favorites>>();
 
     rsx! {
-        header {
-            nav { id: "top-navbar", class: "navbar",
-                div { class: "navbar-brand",
-                    a { href: "#", class: "navbar-item header-title", "アンシンめし"}
-                    a {
-                        id: "top-navbar-burger",
-                        class: "{app_state.read().topNavbarBurgerClass}",
-                        "aria-expanded": "false",
-                        "data-target": "top-navbar-menu",
-                        onclick: move |_| {
-                            if app_state.read().topNavbarBurgerActive {
-                                app_state.write().topNavbarBurgerActive = false;
-                                app_state.write().topNavbarBurgerClass = "navbar-burger".to_string();
-                                app_state.write().topNavbarMenuClass = "navbar-menu".to_string();
-                            } else {
-                                app_state.write().topNavbarBurgerActive = true;
-                                app_state
-                                    .write()
-                                    .topNavbarBurgerClass = "navbar-burger is-active".to_string();
-                                app_state.write().topNavbarMenuClass = "navbar-menu is-active".to_string();
-                            }
-                        },
-                        span { "aria-hidden": "true" }
-                        span { "aria-hidden": "true" }
-                        span { "aria-hidden": "true" }
-                        span { "aria-hidden": "true" }
-                    }
+        div { class: "field",
+            label { class: "checkbox",
+                input {
+                    r#type: "checkbox",
+                    checked: app_state.read().favoritesOnly,
+                    onchange: move |event| app_state.write().favoritesOnly = event.checked(),
                 }
-                div {
-                    id: "top-navbar-menu",
-                    class: "{app_state.read().topNavbarMenuClass}",
-                    div { class: "navbar-start" }
-                    div { class: "navbar-end",
-                        div { class: "navbar-item",
-                            div { class: "buttons",
-                                a {
-                                    href: "#",
-                                    class: "Header-link",
-                                    "data-target": "about-modal",
-                                    onclick: move |_| {
-                                        if app_state.read().aboutModalActive {
-                                            app_state.write().aboutModalActive = false;
-                                            app_state.write().aboutModalClass = "modal".to_string();
-                                        } else {
-                                            app_state.write().aboutModalActive = true;
-                                            app_state.write().aboutModalClass = "modal is-active".to_string();
-                                        }
-                                    },
-                                    "このサイトについて"
-                                }
-                                a {
-                                    href: "#",
-                                    class: "Header-link",
-                                    "data-target": "terms-of-use-modal",
-                                    onclick: move |_| {
-                                        if app_state.read().termsOfUseModalActive {
-                                            app_state.write().termsOfUseModalActive = false;
-                                            app_state.write().termsOfUseModalClass = "modal".to_string();
-                                        } else {
-                                            app_state.write().termsOfUseModalActive = true;
-                                            app_state.write().termsOfUseModalClass = "modal is-active".to_string();
-                                        }
-                                    },
-                                    "利用規約"
-                                }
-                                a {
-                                    href: "#",
-                                    class: "Header-link",
-                                    "data-target": "privacy-policy-modal",
-                                    onclick: move |_| {
-                                        if app_state.read().privacyPolicyModalActive {
-                                            app_state.write().privacyPolicyModalActive = false;
-                                            app_state.write().privacyPolicyModalClass = "modal".to_string();
-                                        } else {
-                                            app_state.write().privacyPolicyModalActive = true;
-                                            app_state.write().privacyPolicyModalClass = "modal is-active".to_string();
-                                        }
-                                    },
+                " お気に入りのみ表示 ({favorites.read().0.len()})"
+            }
+        }
+    }
+}
 
-                                    "個人情報保護方針"
+/// お気に入りの書き出しと読み込み、データから見つからなくなったお気に入りを表示する。
+#[component]
+fn FavoritesPanel() -> Element {
+    let data_state = consume_context::<Memo<DataState>>();
+    let mut favorites = consume_context::<Signal<favorites::Favorites>>();
+    let mut message = use_signal(|| None::<(bool, String)>);
+
+    let export_href = format!(
+        "data:application/json;charset=utf-8,{}",
+        String::from(js_sys::encode_uri_component(&favorites.read().export()))
+    );
+    let missing = favorites
+        .read()
+        .missing(&data_state.read().dataset.as_ref()?.items)
+        .into_iter()
+        .cloned()
+        .collect::<Vec<favorites::Favorite>>();
+    rsx! {
+        div { class: "box",
+            div { class: "buttons",
+                a {
+                    class: "button is-small",
+                    href: "{export_href}",
+                    download: "anshin-meshi-favorites.json",
+                    "ファイルに書き出す"
+                }
+                label { class: "button is-small",
+                    "ファイルから読み込む"
+                    input {
+                        class: "is-hidden",
+                        r#type: "file",
+                        accept: "application/json,.json",
+                        onchange: move |event| async move {
+                            let Some(engine) = event.files() else {
+                                return;
+                            };
+                            for name in engine.files() {
+                                let imported = engine
+                                    .read_file_to_string(&name)
+                                    .await
+                                    .ok_or_else(|| anyhow::anyhow!("Failed to read {}", name))
+                                    .and_then(|json| favorites::parse_import(&json));
+                                match imported {
+                                    Ok(imported) => {
+                                        let added = favorites.write().merge(imported);
+                                        storage::save(storage::FAVORITES_KEY, &*favorites.read());
+                                        message.set(Some((true, format!("{} 件のお気に入りを追加しました。", added))));
+                                    }
+                                    Err(err) => {
+                                        log::warn!("Failed to import favorites: {:?}", err);
+                                        message.set(Some((false, "お気に入りのファイルを読み込めませんでした。".to_string())));
+                                    }
                                 }
                             }
                         }
                     }
                 }
             }
-            div { class: "container px-3 pt-3",
-                div { class: "control has-icons-left",
-                    input {
-                        class: "input is-medium",
-                        r#type: "text",
-                        placeholder: "商品名を入力してください",
-                        oninput: move |event| app_state.write().searchInput = event.value()
+            p { class: "help", "書き出したファイルを家族の端末で読み込むと、お気に入りを共有できます。" }
+            if let Some((success, text)) = message() {
+                p { class: if success { "help is-success" } else { "help is-danger" }, "{text}" }
+            }
+            if !missing.is_empty() {
+                article { class: "message is-warning is-small mt-3",
+                    div { class: "message-body",
+                        p { "次のお気に入りは現在のデータに見つかりません。掲載が終了した可能性があります。" }
+                        ul {
+                            for favorite in missing {
+                                li {
+                                    span { class: "tag is-warning mr-2", "見つかりません" }
+                                    if favorite.productName.is_empty() {
+                                        "届出番号 {favorite.notificationNumber}"
+                                    } else {
+                                        "{favorite.productName}（届出番号 {favorite.notificationNumber}）"
+                                    }
+                                    button {
+                                        class: "button is-small is-text",
+                                        onclick: move |_| {
+                                            favorites.write().remove(&favorite.notificationNumber);
+                                            storage::save(storage::FAVORITES_KEY, &*favorites.read());
+                                        },
+                                        "お気に入りから外す"
+                                    }
+                                }
+                            }
+                        }
                     }
-                    span { class: "icon is-medium is-left", Icon { width: 24, height: 24, icon: IoSearch } }
                 }
             }
         }
@@ -271,23 +1821,112 @@ fn Header() -> Element {
 }
 
 #[component]
-fn TableRow(item: ReadOnlySignal<Data>) -> Element {
+fn SortOrderSelect() -> Element {
     let mut app_state = consume_context::<Signal<AppState>>();
 
+    rsx! {
+        div { class: "select is-small",
+            select {
+                "aria-label": "並び順",
+                onchange: move |event| {
+                    app_state.write().sortOrder = event.value().parse().unwrap_or_default();
+                    sync_search_params(app_state, false);
+                },
+                for order in SortOrder::ALL {
+                    option {
+                        value: "{order}",
+                        selected: app_state.read().sortOrder == order,
+                        "{order.label()}"
+                    }
+                }
+            }
+        }
+    }
+}
+
+/** 一覧に表示する、検索語に一致した部分の前後の文字数 */
+const SNIPPET_CONTEXT: usize = 30;
+
+/// 商品一覧の 1 行。
+///
+/// 入力のたびにすべての行の `Data` を複製しないよう、商品は `Dataset::items` の中での位置で受け取る。
+/// `field` は検索で一致した項目、`matches` はその項目を正規化した文字列で一致したバイト範囲。
+/// 商品名に一致した場合は商品名を、それ以外の項目に一致した場合は一致した部分の前後を強調して表示する。
+#[component]
+fn TableRow(position: usize, field: Option<SearchField>, matches: Vec<Range<usize>>, show_field: bool) -> Element {
+    let app_state = consume_context::<Signal<AppState>>();
+    let data_state = consume_context::<Memo<DataState>>();
+    let search_index = consume_context::<Memo<SearchIndex>>();
+    let recently_viewed = consume_context::<Signal<history::RecentlyViewed>>();
+
+    let state = data_state.read();
+    let item = state.dataset.as_ref()?.items.get(position)?;
+    let notification_number = item.notificationNumber.clone();
+    let product_name = &item.productName;
+    // 検索時に正規化した文字列をそのまま使い、一致した範囲を元の文字列の範囲に戻す
+    let original_matches = |field: SearchField| {
+        search_index
+            .read()
+            .text(position, field)
+            .map(|normalized| matches.iter().filter_map(|range| normalized.original_range(range.clone())).collect::<Vec<_>>())
+            .unwrap_or_default()
+    };
+    let (name_segments, snippet) = match field {
+        Some(SearchField::ProductName) => (highlight::segments(product_name, &original_matches(SearchField::ProductName)), None),
+        Some(field) => {
+            let text = field.text(item);
+            let snippet = highlight::snippet(&text, &original_matches(field), SNIPPET_CONTEXT);
+            (highlight::segments(product_name, &[]), Some(snippet))
+        }
+        None => (highlight::segments(product_name, &[]), None),
+    };
     rsx! {
         tr { onclick: move |_| {
-                log::info!("click: {:?}", item());
-                if app_state.read().detailModalActive {
-                    app_state.write().detailModalActive = false;
-                    app_state.write().detailModalClass = "modal".to_string();
-                    app_state.write().detailModalData = Data::new();
-                } else {
-                    app_state.write().detailModalActive = true;
-                    app_state.write().detailModalClass = "modal is-active".to_string();
-                    app_state.write().detailModalData = item();
+                if let Some(item) = data_state.peek().dataset.as_ref().and_then(|dataset| dataset.items.get(position)) {
+                    log::info!("click: {:?}", item);
+                    record_viewed(recently_viewed, item);
+                    open_modal(app_state, Route::Product { notificationNumber: item.notificationNumber.clone() });
                 }
             },
-            td { "{item().productName}" }
+            td {
+                FavoriteButton { notificationNumber: notification_number.clone(), label: false }
+                CompareButton { notificationNumber: notification_number, label: false }
+                AssessmentTag { assessment: item.assessment.clone(), class: "mr-2" }
+                Highlighted { segments: name_segments }
+                if let (true, Some(field)) = (show_field, field) {
+                    span { class: "tag is-info is-light ml-2", "{field.label()}" }
+                }
+                if let Some(snippet) = snippet {
+                    p { class: "is-size-7 has-text-grey mt-1",
+                        Highlighted { segments: snippet }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 検索語に一致した部分を `<mark>` で囲んで表示する。
+#[component]
+fn Highlighted(segments: Vec<highlight::Segment>) -> Element {
+    rsx! {
+        for segment in segments {
+            if segment.marked {
+                mark { "{segment.text}" }
+            } else {
+                "{segment.text}"
+            }
+        }
+    }
+}
+
+#[component]
+fn AssessmentTag(assessment: Assessment, class: Option<String>) -> Element {
+    rsx! {
+        span {
+            class: "tag {assessment.tag_class()} {class.unwrap_or_default()}",
+            title: "ASCON 総合評価判定",
+            "{assessment.label()}"
         }
     }
 }
@@ -300,12 +1939,32 @@ fn Loading() -> Element {
 
 }
 
+/// URL を持つモーダルを開く。閉じたときに開く前のページへ戻れるよう履歴に追加する。
+fn open_modal(mut app_state: Signal<AppState>, route: Route) {
+    app_state.write().modalOpenedFromList = true;
+    navigator().push(route);
+}
+
+/// URL を持つモーダルを閉じる。
+///
+/// アプリ内から開いた場合はブラウザの戻ると同じく履歴を戻り、
+/// URL から直接開いた場合は今の検索条件のトップページに置き換える。
+fn close_modal(mut app_state: Signal<AppState>) {
+    if app_state.read().modalOpenedFromList {
+        app_state.write().modalOpenedFromList = false;
+        navigator().go_back();
+    } else {
+        let route = search_route(&app_state.read());
+        navigator().replace(route);
+    }
+}
+
 #[component]
-fn DetailModal() -> Element {
-    let mut app_state = consume_context::<Signal<AppState>>();
+fn DetailModal(item: Option<Data>) -> Element {
+    let app_state = consume_context::<Signal<AppState>>();
 
     rsx! {
-        div { id: "detail-modal", class: "{app_state.read().detailModalClass}",
+        div { id: "detail-modal", class: "modal is-active",
             div { class: "modal-background" }
             div { class: "modal-card p-4",
                 header { class: "modal-card-head",
@@ -313,23 +1972,75 @@ fn DetailModal() -> Element {
                     button {
                         class: "modal-close is-large",
                         "aria-label": "close",
-                        onclick: move |_| {
-                            app_state.write().detailModalActive = false;
-                            app_state.write().detailModalClass = "modal".to_string();
-                        }
+                        onclick: move |_| close_modal(app_state)
+                    }
+                }
+                section { class: "modal-card-body",
+                    if let Some(item) = &item {
+                        DetailContent { item: item.clone() }
+                        SimilarProducts { notificationNumber: item.notificationNumber.clone() }
+                    } else {
+                        p { "該当する商品がありません。" }
                     }
                 }
-                section { class: "modal-card-body", DetailContent {} }
                 footer { class: "modal-card-foot",
                     div { class: "buttons",
+                        if let Some(item) = &item {
+                            FavoriteButton { notificationNumber: item.notificationNumber.clone(), label: true }
+                            CompareButton { notificationNumber: item.notificationNumber.clone(), label: true }
+                        }
                         button {
                             class: "button",
+                            onclick: move |_| close_modal(app_state),
+                            "閉じる"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 表示しようとする機能性の似た商品を、より評価の高い商品を先にして表示する。
+#[component]
+fn SimilarProducts(notificationNumber: String) -> Element {
+    let app_state = consume_context::<Signal<AppState>>();
+    let data_state = consume_context::<Memo<DataState>>();
+    let similarity_index = consume_context::<Memo<similar::SimilarityIndex>>();
+    let recently_viewed = consume_context::<Signal<history::RecentlyViewed>>();
+
+    let similar = {
+        let data_state = data_state.read();
+        let items = &data_state.dataset.as_ref()?.items;
+        similarity_index
+            .read()
+            .similar(items, &notificationNumber)
+            .into_iter()
+            .map(|similar| (similar.item.clone(), similar.score, similar.better))
+            .collect::<Vec<(Data, f32, bool)>>()
+    };
+    if similar.is_empty() {
+        return rsx! {};
+    }
+    rsx! {
+        div { class: "content",
+            h3 { "似た商品" }
+            p { class: "is-size-7", "表示しようとする機能性の似た商品です。この商品より評価の高い商品を先に表示します。" }
+            ul {
+                for (item, score, better) in similar {
+                    li { class: "mb-1",
+                        AssessmentTag { assessment: item.assessment.clone(), class: "mr-2" }
+                        a {
                             onclick: move |_| {
-                                app_state.write().detailModalActive = false;
-                                app_state.write().detailModalClass = "modal".to_string();
+                                record_viewed(recently_viewed, &item);
+                                open_modal(app_state, Route::Product { notificationNumber: item.notificationNumber.clone() });
                             },
-                            "閉じる"
+                            "{item.productName}"
                         }
+                        if better {
+                            span { class: "tag is-success is-light ml-2", "より評価が高い" }
+                        }
+                        span { class: "has-text-grey is-size-7 ml-2", "類似度 {(score * 100.0).round()}%" }
                     }
                 }
             }
@@ -338,23 +2049,83 @@ fn DetailModal() -> Element {
 }
 
 #[component]
-fn DetailContent() -> Element {
+fn DetailContent(item: Data) -> Element {
     let app_state = consume_context::<Signal<AppState>>();
+    let data_state = consume_context::<Memo<DataState>>();
+    let search_index = consume_context::<Memo<SearchIndex>>();
+    let concern_index = consume_context::<Memo<concern::ConcernIndex>>();
 
+    let notifier_key = notifier::notifier_key(&item.notifierName);
+    let concerns = concern_index.read().concerns_of(&item.notificationNumber).to_vec();
+    let position = data_state
+        .read()
+        .dataset
+        .as_ref()
+        .and_then(|dataset| dataset.items.iter().position(|data| data.notificationNumber == item.notificationNumber));
+    // 検索中の項目の検索語に一致した部分を強調する
+    let marked = |field: SearchField| {
+        let state = app_state.read();
+        let text = field.text(&item);
+        let ranges = if state.searchMode == SearchMode::AllFields || field == SearchField::ProductName {
+            // 検索用のインデックスにある商品は、インデックスの正規化した文字列で探す
+            let search_index = search_index.read();
+            match position.and_then(|position| search_index.text(position, field)) {
+                Some(normalized) => search::match_ranges(&normalized.text, &normalize::normalize(&state.searchInput))
+                    .into_iter()
+                    .filter_map(|range| normalized.original_range(range))
+                    .collect(),
+                None => highlight::find(&text, &state.searchInput),
+            }
+        } else {
+            Vec::new()
+        };
+        highlight::segments(&text, &ranges)
+    };
     rsx! {
         div { class: "content",
             h3 { "届出番号" }
-            p { "{app_state.read().detailModalData.notificationNumber}" }
+            p {
+                Highlighted { segments: marked(SearchField::NotificationNumber) }
+            }
             h3 { "商品名" }
-            p { "{app_state.read().detailModalData.productName}" }
+            p {
+                Highlighted { segments: marked(SearchField::ProductName) }
+            }
             h3 { "届出者名" }
-            p { "{app_state.read().detailModalData.notifierName}" }
+            p {
+                a {
+                    title: "この届出者の商品を一覧する",
+                    onclick: move |_| open_modal(app_state, Route::Notifier { name: notifier_key.clone() }),
+                    Highlighted { segments: marked(SearchField::NotifierName) }
+                }
+            }
             h3 { "表示しようとする機能性" }
-            p { "{app_state.read().detailModalData.functionalityToDisplay}" }
+            p {
+                Highlighted { segments: marked(SearchField::FunctionalityToDisplay) }
+            }
+            if !concerns.is_empty() {
+                div { class: "tags",
+                    for concern in concerns {
+                        span { class: "tag is-rounded is-light", "{concern.label}" }
+                    }
+                }
+            }
             h3 { "ASCON 総合評価判定" }
-            p { "{app_state.read().detailModalData.assessment}" }
+            p {
+                AssessmentTag { assessment: item.assessment.clone(), class: "is-medium" }
+            }
             h3 { "論文採用の根拠/機能性エビデンスの総評" }
-            p { "{app_state.read().detailModalData.generalReviewOfEvidence}" }
+            p {
+                Highlighted { segments: marked(SearchField::GeneralReviewOfEvidence) }
+            }
+            if let Some(functional_ingredient) = &item.functionalIngredient {
+                h3 { "機能性関与成分名" }
+                p { "{functional_ingredient}" }
+            }
+            if let Some(notification_date) = &item.notificationDate {
+                h3 { "届出日" }
+                p { "{notification_date}" }
+            }
         }
     }
 }
@@ -396,6 +2167,20 @@ fn AboutModal() -> Element {
     }
 }
 
+/// 表示しているデータの取得元と取得日時。
+#[component]
+fn DataSourceInfo() -> Element {
+    let data_state = consume_context::<Memo<DataState>>();
+    let data_state = data_state.read();
+
+    let dataset = data_state.dataset.as_ref()?;
+    let source = if dataset.source.is_empty() { "不明" } else { &dataset.source };
+    rsx! {
+        li { "データの取得元: {source}" }
+        li { "データの取得日時: {format_timestamp(dataset.fetchedAt)}" }
+    }
+}
+
 #[component]
 fn AboutContent() -> Element {
     rsx! {
@@ -414,15 +2199,34 @@ fn AboutContent() -> Element {
                 li {
                     "サイトを開いたらデータの取得が完了するのを待ちます。データの取得が完了すると商品名一覧が表示されます。"
                 }
-                li { "商品名の検索窓に検索したい商品名を入力します。" }
+                li { "商品名の検索窓に検索したい商品名を入力します。「すべての項目で検索」を選ぶと届出者名や表示しようとする機能性からも検索できます。" }
+                li { "検索語に一致した部分は色付きで表示されます。商品名以外の項目に一致した場合は、一致した部分の前後の文章が商品名の下に表示されます。" }
+                li { "ASCON 総合評価判定のチェックボックスを選ぶと、その判定の商品だけに絞り込めます。" }
+                li { "「比較」を押した商品 (最大 {compare::MAX_ITEMS} 件) を並べて比較できます。比較しているページの URL を共有すると、同じ比較を開けます。" }
+                li { "機能性表示評価成績の「似た商品」には、表示しようとする機能性の似た商品を、より評価の高い商品を先にして表示します。" }
+                li { "ヘッダーの「統計」から、評価の分布や届出者ごとの商品数などのデータ全体の統計をグラフで見られます。" }
+                li { "「健康の悩みから探す」で血圧や睡眠などの悩みを選ぶと、表示しようとする機能性からその悩みに分類した商品に絞り込めます。" }
+                li { "機能性表示評価成績の届出者名を押すと、その会社の商品と評価の分布を一覧できます。" }
+                li { "検索窓が空のときは、最近見た商品が商品一覧の上に表示されます。" }
+                li { "商品名の左の星を押すとお気に入りに追加できます。お気に入りはファイルに書き出して、家族の端末で読み込めます。" }
+                li { "検索語・絞り込み・並び順はページの URL に保存されるので、ブックマークや共有ができます。" }
+                li {
+                    "スマートフォンのブラウザのメニューから「ホーム画面に追加」すると、アプリのように起動できます。通信できない場所でも前回取得したデータを閲覧できます。"
+                }
+                li { "「新着・変更」では、前回の訪問から追加・変更・削除された商品と、評価などの変更内容を確認できます。" }
+                li { "表示中にデータが更新されるとお知らせが表示されます。「反映する」を押すと、検索条件を保ったまま最新のデータに切り替わります。" }
                 li {
                     "商品名一覧から商品名をタップすると機能性表示評価成績が表示されます。"
                 }
+                li {
+                    "機能性表示評価成績を表示しているページの URL をブックマークや共有すると、その商品の評価成績を直接開けます。"
+                }
             }
             h3 { "サイト情報" }
             ul {
                 li { "サイト名: アンシンめし"}
                 li { "バージョン: {env::APP_VERSION}" }
+                DataSourceInfo {}
                 li {
                     "Repository: "
                     a { href: env::APP_GITHUB_URL, target: "_blank", "GitHub" }
@@ -607,6 +2411,9 @@ fn PrivacyPolicyContent() -> Element {
                 }
                 "をご参照ください。"
             }
+            p {
+                "このウェブサイトは、最近見た商品の履歴、お気に入り、前回取得したデータをお使いの端末のブラウザ (localStorage) に保存します。これらの情報はお使いの端末の中だけに保存され、サーバーに送信されることはありません。最近見た商品の履歴は、商品一覧の「履歴を消去」から消去できます。"
+            }
             p {
                 "Cookie の使用に関する設定を変更したい場合は、ウェブブラウザの設定を変更して、Cookie の使用を管理することができます。ただし、Cookie の無効化または削除は、ウェブサイトの機能やサービスの一部を制限する可能性があります。"
             }
@@ -635,8 +2442,96 @@ fn Footer() -> Element {
     }
 }
 
-async fn get_data() -> Result<Vec<Data>> {
-    let url = format!("https://script.google.com/macros/s/{}/exec?v=v{}", env::APP_GAS_DEPLOYMENT_ID, env::APP_API_VERSION);
-    let data = reqwest::get(url).await?.json::<Vec<Data>>().await?;
-    Ok(data)
+/// UNIX 時間のミリ秒を `2024/04/01 12:34` の形式にする。
+fn format_timestamp(timestamp: f64) -> String {
+    let date = js_sys::Date::new_0();
+    date.set_time(timestamp);
+    format!(
+        "{}/{:02}/{:02} {:02}:{:02}",
+        date.get_full_year(),
+        date.get_month() + 1,
+        date.get_date(),
+        date.get_hours(),
+        date.get_minutes()
+    )
+}
+
+/// 表示するデータを取得し、成功した場合は次回の表示用にキャッシュする。
+async fn load_dataset() -> Result<Dataset> {
+    let dataset = fetch_dataset().await?;
+    cache_dataset(&dataset);
+    Ok(dataset)
+}
+
+/// データを取得する。キャッシュには保存しない。
+async fn fetch_dataset() -> Result<Dataset> {
+    let (source, response) = get_data().await?;
+    Ok(Dataset::new(&source, response))
+}
+
+/// 最初の取得元だけからデータを取得する。取得に失敗しても次の取得元は試さない。キャッシュには保存しない。
+async fn fetch_primary_dataset() -> Result<Dataset> {
+    let source = data_source::primary();
+    let policy = retry::RetryPolicy::from_config();
+    let response = retry::with_retry(&policy, || source.fetch()).await?;
+    Ok(Dataset::new(&source, response))
+}
+
+/// 表示するデータを次回の表示やオフラインのためにキャッシュする。
+///
+/// サンプルデータで本物のデータのキャッシュを上書きしないよう、サンプルデータは保存しない。
+fn cache_dataset(dataset: &Dataset) {
+    if !dataset.fromFixture {
+        storage::save(&storage::data_cache_key(), dataset);
+    }
+}
+
+/// 設定した取得元を順に試し、最初に取得できた取得元とデータを返す。
+///
+/// データがアプリより新しい形式の場合は、古いデータで代用せずにエラーを返す。
+async fn get_data() -> Result<(data_source::AnyDataSource, api::DataResponse)> {
+    let policy = retry::RetryPolicy::from_config();
+    let mut last_error = None;
+    for source in data_source::configured() {
+        log::info!("get data from {}", source.name());
+        match retry::with_retry(&policy, || source.fetch()).await {
+            Ok(response) => return Ok((source, response)),
+            Err(err) if err.downcast_ref::<api::UnsupportedVersion>().is_some() => return Err(err),
+            Err(err) => {
+                log::warn!("Failed to get data from {}: {:?}", source.name(), err);
+                last_error = Some(err);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No data source is configured")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn search_query_round_trips_through_url() {
+        for q in ["EPA&DHA", "a=b", "100%", "#1", "1+1", "%26", "ブルーベリー & ルテイン"] {
+            let route = Route::Home {
+                q: SearchQuery(q.to_string()),
+                grade: GradeFilter::default(),
+                concern: "sleep".to_string(),
+                mode: SearchMode::AllFields,
+                sort: SortOrder::Assessment,
+            };
+            let url = route.to_string();
+            match Route::from_str(&url) {
+                Ok(Route::Home { q: parsed, concern, mode, sort, .. }) => {
+                    assert_eq!(parsed.0, q, "{}", url);
+                    assert_eq!(concern, "sleep");
+                    assert_eq!(mode, SearchMode::AllFields);
+                    assert_eq!(sort, SortOrder::Assessment);
+                }
+                _ => panic!("failed to parse {}", url),
+            }
+        }
+    }
 }
@@ -0,0 +1,247 @@
+//! 検索用の文字列正規化
+//!
+//! スマートフォンの IME で入力された検索語と商品データの表記ゆれを吸収するため、
+//! 検索語と `Data` の各フィールドの両方に同じ正規化をかけてから比較する。
+
+use std::ops::Range;
+
+/** 半角カタカナ (U+FF61..=U+FF9D) */
+const HALF_WIDTH_KANA: &str = "｡｢｣､･ｦｧｨｩｪｫｬｭｮｯｰｱｲｳｴｵｶｷｸｹｺｻｼｽｾｿﾀﾁﾂﾃﾄﾅﾆﾇﾈﾉﾊﾋﾌﾍﾎﾏﾐﾑﾒﾓﾔﾕﾖﾗﾘﾙﾚﾛﾜﾝ";
+/** `HALF_WIDTH_KANA` に対応する全角文字 */
+const FULL_WIDTH_KANA: &str = "。「」、・ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン";
+/** 小書きカナ */
+const SMALL_KANA: &str = "ァィゥェォッャュョヮヵヶ";
+/** `SMALL_KANA` に対応する通常のカナ */
+const LARGE_KANA: &str = "アイウエオツヤユヨワカケ";
+/** 濁点を付けられるカナ (文字コード +1 で濁音になるもの) */
+const VOICEABLE_KANA: &str = "カキクケコサシスセソタチツテトハヒフヘホ";
+/** 半濁点を付けられるカナ (文字コード +2 で半濁音になるもの) */
+const SEMI_VOICEABLE_KANA: &str = "ハヒフヘホ";
+/** 長音記号として扱う文字 */
+const LONG_VOWEL_MARKS: &str = "ーｰ－‐‑–—―−～〜";
+
+/// 検索用に文字列を正規化する。
+///
+/// - 全角英数記号を半角に、半角カナを全角に揃える
+/// - ひらがなをカタカナに揃える
+/// - 分離した濁点・半濁点を直前のカナと合成する
+/// - 小書きカナを通常のカナに揃える
+/// - カナの後ろの長音記号 (とその代用文字) を取り除く
+/// - 英字を小文字に揃える
+pub fn normalize(input: &str) -> String {
+    NormalizedText::new(input).text
+}
+
+/// 正規化した文字列と、正規化後の各文字が元の文字列のどの範囲から作られたか。
+///
+/// 正規化で文字数が変わるため、正規化後の文字列で見つけた検索語の位置を元の文字列の位置に
+/// 戻すのに使う。
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedText {
+    pub text: String,
+    /** 正規化後の各文字の (`text` でのバイト位置, 元の文字列でのバイト範囲) */
+    spans: Vec<(usize, Range<usize>)>,
+}
+
+impl NormalizedText {
+    pub fn new(input: &str) -> Self {
+        let mut chars: Vec<(char, Range<usize>)> = Vec::with_capacity(input.len());
+
+        for (offset, c) in input.char_indices() {
+            let end = offset + c.len_utf8();
+            match c {
+                // 濁点・半濁点 (結合文字 / 全角 / 半角)
+                '\u{3099}' | '\u{309B}' | '\u{FF9E}' => {
+                    compose_voiced(&mut chars);
+                    extend_last(&mut chars, end);
+                }
+                '\u{309A}' | '\u{309C}' | '\u{FF9F}' => {
+                    compose_semi_voiced(&mut chars);
+                    extend_last(&mut chars, end);
+                }
+                // 長音記号は全角英数記号の変換で `-` や `~` になる前に、直前の文字を見て判定する
+                _ if LONG_VOWEL_MARKS.contains(c) => chars.push((c, offset..end)),
+                _ => chars.push((fold_char(c), offset..end)),
+            }
+        }
+
+        let mut text = String::with_capacity(input.len());
+        let mut spans: Vec<(usize, Range<usize>)> = Vec::with_capacity(chars.len());
+        let mut previous: Option<char> = None;
+        for (c, range) in chars {
+            let c = if LONG_VOWEL_MARKS.contains(c) {
+                if previous.is_some_and(is_katakana) {
+                    // 取り除いた長音記号は直前の文字の範囲に含め、強調するときに一緒に囲む
+                    if let Some((_, last)) = spans.last_mut() {
+                        last.end = range.end;
+                    }
+                    continue;
+                }
+                fold_char(c)
+            } else {
+                c
+            };
+            let c = fold_small_kana(c);
+            for lower in c.to_lowercase() {
+                spans.push((text.len(), range.clone()));
+                text.push(lower);
+            }
+            previous = Some(c);
+        }
+        Self { text, spans }
+    }
+
+    /// 正規化後の文字列のバイト範囲を、元の文字列のバイト範囲に戻す。
+    ///
+    /// 正規化で取り除いた長音記号は直前の文字に含めるため、`ブルーベリー` の `ブルベリ` は
+    /// 元の文字列の `ブルーベリー` 全体になる。範囲に文字が含まれない場合は `None` を返す。
+    pub fn original_range(&self, range: Range<usize>) -> Option<Range<usize>> {
+        let first = self
+            .spans
+            .partition_point(|(offset, _)| *offset < range.start);
+        let last = self
+            .spans
+            .partition_point(|(offset, _)| *offset < range.end);
+        if first >= last {
+            return None;
+        }
+        Some(self.spans[first].1.start..self.spans[last - 1].1.end)
+    }
+}
+
+/// 合成した濁点・半濁点を直前の文字の範囲に含める。
+fn extend_last(chars: &mut [(char, Range<usize>)], end: usize) {
+    if let Some((_, range)) = chars.last_mut() {
+        range.end = end;
+    }
+}
+
+/// 1 文字単位で表記ゆれを吸収する。
+fn fold_char(c: char) -> char {
+    match c {
+        // 全角スペース
+        '\u{3000}' => ' ',
+        // 全角英数記号
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        // 半角カナ
+        '\u{FF61}'..='\u{FF9D}' => HALF_WIDTH_KANA
+            .chars()
+            .position(|half| half == c)
+            .and_then(|i| FULL_WIDTH_KANA.chars().nth(i))
+            .unwrap_or(c),
+        // ひらがな
+        '\u{3041}'..='\u{3096}' | '\u{309D}'..='\u{309E}' => {
+            char::from_u32(c as u32 + 0x60).unwrap_or(c)
+        }
+        _ => c,
+    }
+}
+
+fn fold_small_kana(c: char) -> char {
+    SMALL_KANA
+        .chars()
+        .position(|small| small == c)
+        .and_then(|i| LARGE_KANA.chars().nth(i))
+        .unwrap_or(c)
+}
+
+fn compose_voiced(chars: &mut [(char, Range<usize>)]) {
+    if let Some((last, _)) = chars.last_mut() {
+        if VOICEABLE_KANA.contains(*last) {
+            *last = char::from_u32(*last as u32 + 1).unwrap_or(*last);
+        } else if *last == 'ウ' {
+            *last = 'ヴ';
+        }
+    }
+}
+
+fn compose_semi_voiced(chars: &mut [(char, Range<usize>)]) {
+    if let Some((last, _)) = chars.last_mut() {
+        if SEMI_VOICEABLE_KANA.contains(*last) {
+            *last = char::from_u32(*last as u32 + 2).unwrap_or(*last);
+        }
+    }
+}
+
+fn is_katakana(c: char) -> bool {
+    matches!(c, '\u{30A1}'..='\u{30FA}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize, NormalizedText};
+
+    #[test]
+    fn hiragana_matches_katakana() {
+        assert_eq!(normalize("ぎゃば"), normalize("ギャバ"));
+        assert_eq!(normalize("るていん"), "ルテイン");
+    }
+
+    #[test]
+    fn full_width_ascii_matches_half_width() {
+        assert_eq!(normalize("ＤＨＡ"), "dha");
+        assert_eq!(normalize("ＥＰＡ＆ＤＨＡ　１２３"), "epa&dha 123");
+    }
+
+    #[test]
+    fn case_is_folded() {
+        assert_eq!(normalize("GABA"), normalize("gaba"));
+        assert_eq!(normalize("Ｇａｂａ"), "gaba");
+    }
+
+    #[test]
+    fn half_width_kana_with_voiced_marks() {
+        assert_eq!(normalize("ｷﾞｬﾊﾞ"), "ギヤバ");
+        assert_eq!(normalize("ﾌﾟﾛﾃｲﾝ"), normalize("プロテイン"));
+        assert_eq!(normalize("ｳﾞｨﾀﾐﾝ"), normalize("ヴィタミン"));
+    }
+
+    #[test]
+    fn combining_voiced_marks_are_composed() {
+        assert_eq!(normalize("カ\u{3099}セリ菌"), normalize("ガセリ菌"));
+        assert_eq!(normalize("ヒ\u{309A}ーマン"), normalize("ピーマン"));
+        assert_eq!(normalize("は゛な"), normalize("バナ"));
+    }
+
+    #[test]
+    fn small_kana_are_folded() {
+        assert_eq!(normalize("ギヤバ"), normalize("ギャバ"));
+        assert_eq!(normalize("サプリメント"), normalize("さぷりめんと"));
+        assert_eq!(normalize("ビフィズス菌"), normalize("びふいずす菌"));
+    }
+
+    #[test]
+    fn long_vowel_marks_are_removed_after_kana() {
+        assert_eq!(normalize("ブルーベリー"), normalize("ブルベリ"));
+        assert_eq!(normalize("ｺｰﾋｰ"), normalize("コーヒー"));
+        assert_eq!(normalize("こ〜ひ〜"), normalize("コーヒー"));
+        assert_eq!(normalize("サーバ"), normalize("サーバー"));
+        assert_eq!(normalize("コ－ヒ－"), normalize("コーヒー"));
+        assert_eq!(normalize("コ～ヒ～"), normalize("コーヒー"));
+    }
+
+    #[test]
+    fn dashes_outside_kana_are_kept() {
+        assert_eq!(normalize("A-1"), "a-1");
+        assert_eq!(normalize("Ａ－１"), "a-1");
+        assert_eq!(normalize("ー"), "ー");
+    }
+
+    #[test]
+    fn kanji_are_unchanged() {
+        assert_eq!(normalize("血圧"), "血圧");
+        assert_eq!(normalize("血圧が高めの方に"), "血圧ガ高メノ方ニ");
+    }
+
+    #[test]
+    fn maps_normalized_ranges_to_original() {
+        let input = "ｷﾞｬﾊﾞとコーヒー";
+        let normalized = NormalizedText::new(input);
+        assert_eq!(normalized.text, "ギヤバトコヒ");
+        let start = normalized.text.find("バ").unwrap();
+        let end = normalized.text.find("ヒ").unwrap() + "ヒ".len();
+        let range = normalized.original_range(start..end).unwrap();
+        assert_eq!(&input[range], "ﾊﾞとコーヒー");
+        assert_eq!(normalized.original_range(0..0), None);
+    }
+}
@@ -0,0 +1,139 @@
+//! 届出者 (会社) ごとの集計
+//!
+//! 同じ会社が「株式会社」「（株）」「(株)」などの表記ゆれで別々の会社に分かれないよう、
+//! 法人の種類の表記と空白を取り除いた名前を会社のキーとする。
+
+use std::collections::HashMap;
+
+use crate::assessment::Grade;
+use crate::normalize::normalize;
+use crate::Data;
+
+/** 取り除く法人の種類の表記。`normalize` をかけた後の表記で、長いものから順に並べる */
+const CORPORATE_DESIGNATIONS: [&str; 14] = [
+    "株式会社",
+    "有限会社",
+    "合同会社",
+    "合資会社",
+    "合名会社",
+    "(株)",
+    "(有)",
+    "(同)",
+    "(資)",
+    "(名)",
+    "㈱",
+    "㈲",
+    "㈾",
+    "㈴",
+];
+
+/// 届出者名から会社のキーを作る。
+pub fn notifier_key(name: &str) -> String {
+    let mut key = normalize(name);
+    for designation in CORPORATE_DESIGNATIONS {
+        key = key.replace(designation, "");
+    }
+    key.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// 会社の商品を評価の高い順に返す。
+pub fn products_of<'a>(items: &'a [Data], key: &str) -> Vec<&'a Data> {
+    let mut products = items
+        .iter()
+        .filter(|item| notifier_key(&item.notifierName) == key)
+        .collect::<Vec<&Data>>();
+    products.sort_by(|a, b| {
+        a.assessment
+            .grade()
+            .cmp(&b.assessment.grade())
+            .then_with(|| a.productName.cmp(&b.productName))
+    });
+    products
+}
+
+/// 会社の表示名。表記ゆれがある場合は最も多く使われている表記を選ぶ。
+pub fn display_name(products: &[&Data]) -> Option<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for item in products {
+        *counts.entry(item.notifierName.trim()).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|(a_name, a_count), (b_name, b_count)| {
+            a_count.cmp(b_count).then_with(|| b_name.cmp(a_name))
+        })
+        .map(|(name, _)| name.to_string())
+}
+
+/// 会社の商品の評価の区分ごとの件数。
+pub fn grade_distribution(products: &[&Data]) -> Vec<(Grade, usize)> {
+    Grade::ALL
+        .into_iter()
+        .map(|grade| {
+            let count = products
+                .iter()
+                .filter(|item| item.assessment.grade() == grade)
+                .count();
+            (grade, count)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assessment::Assessment;
+
+    fn data(number: &str, notifier_name: &str, assessment: Assessment) -> Data {
+        Data {
+            notifierName: notifier_name.to_string(),
+            assessment,
+            ..Data::sample(number)
+        }
+    }
+
+    #[test]
+    fn merges_corporate_designation_variants() {
+        let key = notifier_key("株式会社サンプルヘルス");
+        for name in [
+            "サンプルヘルス株式会社",
+            "（株）サンプルヘルス",
+            "(株) サンプルヘルス",
+            "㈱サンプルヘルス",
+            "株式会社　サンプルヘルス",
+            " サンプル ヘルス ",
+            "ｻﾝﾌﾟﾙﾍﾙｽ(株)",
+        ] {
+            assert_eq!(notifier_key(name), key, "name: {}", name);
+        }
+        assert_ne!(notifier_key("サンプル食品株式会社"), key);
+    }
+
+    #[test]
+    fn aggregates_products_of_a_notifier() {
+        let items = vec![
+            data("A1", "（株）サンプルヘルス", Assessment::C),
+            data("A2", "サンプル食品株式会社", Assessment::A),
+            data("A3", "株式会社 サンプルヘルス", Assessment::A),
+            data(
+                "A4",
+                "株式会社 サンプルヘルス",
+                Assessment::Unknown("".to_string()),
+            ),
+        ];
+        let products = products_of(&items, &notifier_key("サンプルヘルス"));
+        let numbers = products
+            .iter()
+            .map(|item| item.notificationNumber.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(numbers, vec!["A3", "A1", "A4"]);
+        assert_eq!(
+            display_name(&products).as_deref(),
+            Some("株式会社 サンプルヘルス")
+        );
+        let distribution = grade_distribution(&products);
+        assert_eq!(distribution[0], (Grade::A, 1));
+        assert_eq!(distribution[2], (Grade::C, 1));
+        assert_eq!(distribution[5], (Grade::Other, 1));
+    }
+}
@@ -0,0 +1,152 @@
+//! データ取得の再試行
+//!
+//! 通信エラーや Google Apps Script の呼び出し回数の上限などの一時的なエラーの場合は、
+//! ジッターを加えた指数バックオフで待ってから再試行する。
+
+use std::fmt;
+use std::future::Future;
+
+use anyhow::Result;
+
+use crate::env;
+
+/** 一時的なエラー。再試行すれば成功する可能性がある */
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransientError(pub String);
+
+impl fmt::Display for TransientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Transient error: {}", self.0)
+    }
+}
+
+impl std::error::Error for TransientError {}
+
+/** 再試行の設定 */
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /** 最初の試行を除いた再試行の回数 */
+    pub maxRetries: u32,
+    /** 1 回目の再試行までの待ち時間の上限 (ミリ秒)。以降は 2 倍ずつ増やす */
+    pub baseDelayMs: u32,
+    /** 待ち時間の上限 (ミリ秒) */
+    pub maxDelayMs: u32,
+}
+
+impl RetryPolicy {
+    /// `.env` の `APP_MAX_RETRIES` と `APP_RETRY_BASE_DELAY_MS` から作る。
+    pub fn from_config() -> Self {
+        Self {
+            maxRetries: parse_config("APP_MAX_RETRIES", env::APP_MAX_RETRIES, 3),
+            baseDelayMs: parse_config(
+                "APP_RETRY_BASE_DELAY_MS",
+                env::APP_RETRY_BASE_DELAY_MS,
+                1000,
+            ),
+            maxDelayMs: 30_000,
+        }
+    }
+
+    /// `attempt` 回目 (0 始まり) の再試行までの待ち時間を返す。
+    ///
+    /// `random` は 0 以上 1 未満の乱数で、待ち時間の上限にかけて待ち時間をばらつかせる (full jitter)。
+    pub fn delay_ms(&self, attempt: u32, random: f64) -> u32 {
+        let ceiling = self
+            .baseDelayMs
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.maxDelayMs);
+        (ceiling as f64 * random.clamp(0.0, 1.0)) as u32
+    }
+}
+
+/// 設定値を数値として読み取る。読み取れない場合は既定値を使う。
+pub fn parse_config<T: std::str::FromStr + fmt::Display + Copy>(
+    name: &str,
+    value: &str,
+    default: T,
+) -> T {
+    value.parse().unwrap_or_else(|_| {
+        log::warn!("Invalid {}: {:?}, use {}", name, value, default);
+        default
+    })
+}
+
+/// 再試行すれば成功する可能性のあるエラーかどうか。
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    if err.downcast_ref::<TransientError>().is_some() {
+        return true;
+    }
+    if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+        return err.is_timeout()
+            || err.is_request()
+            || err
+                .status()
+                .is_some_and(|status| status.as_u16() == 429 || status.is_server_error());
+    }
+    false
+}
+
+/// `f` が一時的なエラーで失敗した場合に再試行する。
+pub async fn with_retry<T, F, Fut>(policy: &RetryPolicy, mut f: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        match f().await {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt < policy.maxRetries && is_transient(&err) => {
+                let delay = policy.delay_ms(attempt, js_sys::Math::random());
+                log::warn!(
+                    "Retry {}/{} after {}ms: {:?}",
+                    attempt + 1,
+                    policy.maxRetries,
+                    delay,
+                    err
+                );
+                gloo_timers::future::TimeoutFuture::new(delay).await;
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            maxRetries: 5,
+            baseDelayMs: 1000,
+            maxDelayMs: 10_000,
+        }
+    }
+
+    #[test]
+    fn delay_grows_exponentially_up_to_the_limit() {
+        let policy = policy();
+        assert_eq!(policy.delay_ms(0, 0.999_999), 999);
+        assert_eq!(policy.delay_ms(1, 0.5), 1000);
+        assert_eq!(policy.delay_ms(2, 0.5), 2000);
+        assert_eq!(policy.delay_ms(10, 1.0), 10_000);
+        assert_eq!(policy.delay_ms(40, 1.0), 10_000);
+        assert_eq!(policy.delay_ms(3, 0.0), 0);
+    }
+
+    #[test]
+    fn classifies_transient_errors() {
+        assert!(is_transient(&TransientError("quota".to_string()).into()));
+        assert!(!is_transient(&anyhow!("Response is not JSON")));
+    }
+
+    #[test]
+    fn falls_back_to_default_config() {
+        assert_eq!(parse_config("APP_MAX_RETRIES", "5", 3), 5);
+        assert_eq!(parse_config("APP_MAX_RETRIES", "many", 3), 3);
+    }
+}
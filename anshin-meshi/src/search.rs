@@ -0,0 +1,612 @@
+//! 商品データの検索
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::normalize::{normalize, NormalizedText};
+use crate::Data;
+
+/** 検索モード */
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    /** 商品名のみを検索する */
+    #[default]
+    ProductName,
+    /** すべての項目を検索する */
+    AllFields,
+}
+
+/// URL のクエリ文字列での表記。既定値は空文字列とする。
+impl fmt::Display for SearchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchMode::ProductName => write!(f, ""),
+            SearchMode::AllFields => write!(f, "all"),
+        }
+    }
+}
+
+impl FromStr for SearchMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Ok(SearchMode::ProductName),
+            "all" => Ok(SearchMode::AllFields),
+            _ => Err(format!("unknown search mode: {}", s)),
+        }
+    }
+}
+
+/// URL のクエリ文字列の検索語。
+///
+/// ルーターはクエリ文字列全体をデコードしてから `&` と `=` で区切るため、検索語に含まれる
+/// `&` などが区切りと見なされないよう、これらの文字をもう一度エンコードしておく。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery(pub String);
+
+impl fmt::Display for SearchQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '%' | '&' | '=' | '#' | '+' => write!(f, "%{:02X}", c as u32)?,
+                _ => write!(f, "{}", c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SearchQuery {
+    type Err = String;
+
+    /// `%` に続く 2 桁の 16 進数をデコードする。それ以外の `%` はそのまま残す。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = s.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            match hex {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 3;
+                }
+                None => {
+                    decoded.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+        Ok(SearchQuery(String::from_utf8_lossy(&decoded).into_owned()))
+    }
+}
+
+/** 検索結果の並び順 */
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    /** 一致した項目の優先度順 */
+    #[default]
+    Relevance,
+    /** ASCON 総合評価判定の高い順 */
+    Assessment,
+    /** 届出番号順 */
+    NotificationNumber,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 3] = [
+        SortOrder::Relevance,
+        SortOrder::Assessment,
+        SortOrder::NotificationNumber,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortOrder::Relevance => "関連度順",
+            SortOrder::Assessment => "評価の高い順",
+            SortOrder::NotificationNumber => "届出番号順",
+        }
+    }
+}
+
+/// URL のクエリ文字列での表記。既定値は空文字列とする。
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortOrder::Relevance => write!(f, ""),
+            SortOrder::Assessment => write!(f, "grade"),
+            SortOrder::NotificationNumber => write!(f, "number"),
+        }
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Ok(SortOrder::Relevance),
+            "grade" => Ok(SortOrder::Assessment),
+            "number" => Ok(SortOrder::NotificationNumber),
+            _ => Err(format!("unknown sort order: {}", s)),
+        }
+    }
+}
+
+/** 検索対象の項目 */
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SearchField {
+    ProductName,
+    NotifierName,
+    FunctionalityToDisplay,
+    NotificationNumber,
+    Assessment,
+    GeneralReviewOfEvidence,
+}
+
+impl SearchField {
+    /** 優先度の高い順に並べたすべての項目 */
+    pub const ALL: [SearchField; 6] = [
+        SearchField::ProductName,
+        SearchField::NotifierName,
+        SearchField::FunctionalityToDisplay,
+        SearchField::NotificationNumber,
+        SearchField::Assessment,
+        SearchField::GeneralReviewOfEvidence,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SearchField::ProductName => "商品名",
+            SearchField::NotifierName => "届出者名",
+            SearchField::FunctionalityToDisplay => "表示しようとする機能性",
+            SearchField::NotificationNumber => "届出番号",
+            SearchField::Assessment => "ASCON 総合評価判定",
+            SearchField::GeneralReviewOfEvidence => "論文採用の根拠/機能性エビデンスの総評",
+        }
+    }
+    /// 正規化する前の項目の値。
+    pub fn text(&self, data: &Data) -> String {
+        match self {
+            SearchField::ProductName => data.productName.clone(),
+            SearchField::NotifierName => data.notifierName.clone(),
+            SearchField::FunctionalityToDisplay => data.functionalityToDisplay.clone(),
+            SearchField::NotificationNumber => data.notificationNumber.clone(),
+            SearchField::Assessment => data.assessment.to_string(),
+            SearchField::GeneralReviewOfEvidence => data.generalReviewOfEvidence.clone(),
+        }
+    }
+}
+
+/** 検索用に正規化した `Data`。一致した位置を元の文字列に戻せるよう、正規化前との対応も持つ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct NormalizedData {
+    pub notificationNumber: NormalizedText,
+    pub productName: NormalizedText,
+    pub notifierName: NormalizedText,
+    pub functionalityToDisplay: NormalizedText,
+    pub assessment: NormalizedText,
+    pub generalReviewOfEvidence: NormalizedText,
+}
+
+impl NormalizedData {
+    pub fn field(&self, field: SearchField) -> &NormalizedText {
+        match field {
+            SearchField::ProductName => &self.productName,
+            SearchField::NotifierName => &self.notifierName,
+            SearchField::FunctionalityToDisplay => &self.functionalityToDisplay,
+            SearchField::NotificationNumber => &self.notificationNumber,
+            SearchField::Assessment => &self.assessment,
+            SearchField::GeneralReviewOfEvidence => &self.generalReviewOfEvidence,
+        }
+    }
+}
+
+impl From<&Data> for NormalizedData {
+    fn from(data: &Data) -> Self {
+        Self {
+            notificationNumber: NormalizedText::new(&data.notificationNumber),
+            productName: NormalizedText::new(&data.productName),
+            notifierName: NormalizedText::new(&data.notifierName),
+            functionalityToDisplay: NormalizedText::new(&data.functionalityToDisplay),
+            assessment: NormalizedText::new(&data.assessment.to_string()),
+            generalReviewOfEvidence: NormalizedText::new(&data.generalReviewOfEvidence),
+        }
+    }
+}
+
+/** 1 項目分の転置インデックス */
+#[derive(Debug, Default, Clone, PartialEq)]
+struct FieldIndex {
+    /** 文字 → その文字を含む商品の番号 (昇順) */
+    unigrams: HashMap<char, Vec<u32>>,
+    /** 2 文字の組 → その組を含む商品の番号 (昇順) */
+    bigrams: HashMap<(char, char), Vec<u32>>,
+}
+
+impl FieldIndex {
+    fn insert(&mut self, id: u32, text: &str) {
+        let chars = text.chars().collect::<Vec<char>>();
+        for c in &chars {
+            push_posting(self.unigrams.entry(*c).or_default(), id);
+        }
+        for pair in chars.windows(2) {
+            push_posting(self.bigrams.entry((pair[0], pair[1])).or_default(), id);
+        }
+    }
+
+    /// 正規化済みの検索語を含む可能性のある商品の番号を返す。
+    fn candidates(&self, query: &[char]) -> Vec<u32> {
+        let postings = if query.len() == 1 {
+            vec![self.unigrams.get(&query[0])]
+        } else {
+            query
+                .windows(2)
+                .map(|pair| self.bigrams.get(&(pair[0], pair[1])))
+                .collect()
+        };
+        let Some(mut postings) = postings.into_iter().collect::<Option<Vec<&Vec<u32>>>>() else {
+            return Vec::new();
+        };
+        postings.sort_by_key(|posting| posting.len());
+        let (shortest, rest) = postings.split_first().expect("query is not empty");
+        shortest
+            .iter()
+            .filter(|id| rest.iter().all(|posting| posting.binary_search(id).is_ok()))
+            .copied()
+            .collect()
+    }
+}
+
+fn push_posting(posting: &mut Vec<u32>, id: u32) {
+    if posting.last() != Some(&id) {
+        posting.push(id);
+    }
+}
+
+/// 検索用のインデックス。
+///
+/// データの取得後に一度だけ構築し、キー入力ごとの検索は文字バイグラムの転置インデックスで
+/// 候補を絞り込んでから正規化済みの文字列で確認する。
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SearchIndex {
+    normalized: Vec<NormalizedData>,
+    /** `SearchField::ALL` と同じ順序 */
+    fields: [FieldIndex; 6],
+}
+
+impl SearchIndex {
+    pub fn new(items: &[Data]) -> Self {
+        let mut index = Self::default();
+        for (id, item) in items.iter().enumerate() {
+            let normalized = NormalizedData::from(item);
+            for (field, field_index) in SearchField::ALL.iter().zip(index.fields.iter_mut()) {
+                field_index.insert(id as u32, &normalized.field(*field).text);
+            }
+            index.normalized.push(normalized);
+        }
+        index
+    }
+
+    /// `items` の `position` 番目の商品の正規化した項目。
+    pub fn text(&self, position: usize, field: SearchField) -> Option<&NormalizedText> {
+        self.normalized
+            .get(position)
+            .map(|normalized| normalized.field(field))
+    }
+
+    fn field_index(&self, field: SearchField) -> &FieldIndex {
+        &self.fields[field as usize]
+    }
+}
+
+/** 検索結果 */
+#[derive(Debug, Clone)]
+pub struct SearchHit<'a> {
+    pub item: &'a Data,
+    /** `items` の中での `item` の位置 */
+    pub position: usize,
+    /** 一致した項目のうち最も優先度の高いもの。検索語が空の場合は `None` */
+    pub field: Option<SearchField>,
+    /** `field` を正規化した文字列で検索語に一致したバイト範囲。元の文字列の範囲には `NormalizedText::original_range` で戻す */
+    pub matches: Vec<Range<usize>>,
+}
+
+/// 正規化した文字列で、正規化した検索語に一致するバイト範囲を前から重ならないように返す。
+pub fn match_ranges(normalized: &str, query: &str) -> Vec<Range<usize>> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    normalized
+        .match_indices(query)
+        .map(|(start, matched)| start..start + matched.len())
+        .collect()
+}
+
+/// 検索語を含む商品を返す。
+///
+/// `index` は `items` から構築したものであること。
+/// `SearchMode::AllFields` の場合は一致した項目の優先度 (`SearchField::ALL` の順) で並べ替える。
+pub fn search<'a>(
+    items: &'a [Data],
+    index: &SearchIndex,
+    query: &str,
+    mode: SearchMode,
+) -> Vec<SearchHit<'a>> {
+    let query = normalize(query);
+    if query.is_empty() {
+        return items
+            .iter()
+            .enumerate()
+            .map(|(position, item)| SearchHit {
+                item,
+                position,
+                field: None,
+                matches: Vec::new(),
+            })
+            .collect();
+    }
+
+    let fields: &[SearchField] = match mode {
+        SearchMode::ProductName => &[SearchField::ProductName],
+        SearchMode::AllFields => &SearchField::ALL,
+    };
+    let query_chars = query.chars().collect::<Vec<char>>();
+    let mut matched = vec![false; items.len()];
+    let mut hits = Vec::new();
+    for field in fields {
+        for id in index.field_index(*field).candidates(&query_chars) {
+            let id = id as usize;
+            if matched[id] {
+                continue;
+            }
+            let matches = match_ranges(&index.normalized[id].field(*field).text, &query);
+            if matches.is_empty() {
+                continue;
+            }
+            matched[id] = true;
+            hits.push(SearchHit {
+                item: &items[id],
+                position: id,
+                field: Some(*field),
+                matches,
+            });
+        }
+    }
+    hits
+}
+
+/// 検索結果を並べ替える。`SortOrder::Relevance` の場合は `search` の順序のままとする。
+pub fn sort_hits(hits: &mut [SearchHit], order: SortOrder) {
+    match order {
+        SortOrder::Relevance => {}
+        SortOrder::Assessment => hits.sort_by_key(|hit| hit.item.assessment.grade()),
+        SortOrder::NotificationNumber => {
+            hits.sort_by(|a, b| a.item.notificationNumber.cmp(&b.item.notificationNumber))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    fn data(number: usize, product: &str, notifier: &str, functionality: &str) -> Data {
+        Data {
+            productName: product.to_string(),
+            notifierName: notifier.to_string(),
+            functionalityToDisplay: functionality.to_string(),
+            generalReviewOfEvidence: format!("{}に関する研究レビュー", functionality),
+            ..Data::sample(&format!("A{:05}", number))
+        }
+    }
+
+    /// インデックスを使わない素朴な検索。結果の比較に使う。
+    fn linear_search<'a>(items: &'a [Data], query: &str, mode: SearchMode) -> Vec<SearchHit<'a>> {
+        let query = normalize(query);
+        let fields: &[SearchField] = match mode {
+            SearchMode::ProductName => &[SearchField::ProductName],
+            SearchMode::AllFields => &SearchField::ALL,
+        };
+        let mut hits = items
+            .iter()
+            .enumerate()
+            .filter_map(|(position, item)| {
+                let normalized = NormalizedData::from(item);
+                fields.iter().find_map(|field| {
+                    let matches = match_ranges(&normalized.field(*field).text, &query);
+                    (!matches.is_empty()).then_some(SearchHit {
+                        item,
+                        position,
+                        field: Some(*field),
+                        matches,
+                    })
+                })
+            })
+            .collect::<Vec<SearchHit>>();
+        hits.sort_by_key(|hit| hit.field);
+        hits
+    }
+
+    fn numbers(hits: &[SearchHit]) -> Vec<String> {
+        hits.iter()
+            .map(|hit| hit.item.notificationNumber.clone())
+            .collect()
+    }
+
+    fn synthetic_items(count: usize) -> Vec<Data> {
+        const PRODUCTS: [&str; 8] = [
+            "ギャバ",
+            "ＤＨＡ",
+            "ルテイン",
+            "ブルーベリー",
+            "ビフィズス菌",
+            "コラーゲン",
+            "ガセリ菌",
+            "ﾌﾟﾛﾃｲﾝ",
+        ];
+        const NOTIFIERS: [&str; 4] = ["株式会社あんしん", "ヘルス食品", "サプリ堂", "健康研究所"];
+        const FUNCTIONALITIES: [&str; 5] = [
+            "血圧が高めの方に",
+            "睡眠の質を高める",
+            "体脂肪を減らす",
+            "目のピント調節を助ける",
+            "肌の潤いを保つ",
+        ];
+        (0..count)
+            .map(|i| {
+                data(
+                    i,
+                    &format!("{}{}", PRODUCTS[i % PRODUCTS.len()], i),
+                    NOTIFIERS[i % NOTIFIERS.len()],
+                    FUNCTIONALITIES[i % FUNCTIONALITIES.len()],
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn encodes_query_delimiters_in_search_query() {
+        let query = SearchQuery("EPA&DHA=100% #1+".to_string());
+        assert_eq!(query.to_string(), "EPA%26DHA%3D100%25 %231%2B");
+        assert_eq!(query.to_string().parse::<SearchQuery>(), Ok(query));
+        assert_eq!("50%off%2".parse::<SearchQuery>().unwrap().0, "50%off%2");
+    }
+
+    #[test]
+    fn ranks_product_name_hits_first() {
+        let items = vec![
+            data(1, "快眠サポート", "ギャバ食品", "睡眠の質を高める"),
+            data(2, "ギャバ", "あんしん", "血圧が高めの方に"),
+        ];
+        let index = SearchIndex::new(&items);
+        let hits = search(&items, &index, "ぎゃば", SearchMode::AllFields);
+        assert_eq!(numbers(&hits), vec!["A00002", "A00001"]);
+        assert_eq!(hits[0].field, Some(SearchField::ProductName));
+        assert_eq!(hits[1].field, Some(SearchField::NotifierName));
+
+        let hits = search(&items, &index, "ぎゃば", SearchMode::ProductName);
+        assert_eq!(numbers(&hits), vec!["A00002"]);
+    }
+
+    #[test]
+    fn all_fields_mode_reports_the_matched_field() {
+        let items = vec![
+            data(1, "快眠サポート", "あんしん", "睡眠の質を高める"),
+            data(2, "ルテイン", "あんしん", "目のピント調節を助ける"),
+            data(3, "ブルーベリー", "あんしん", "目のピント調節を助ける"),
+        ];
+        let index = SearchIndex::new(&items);
+        let fields = |hits: &[SearchHit]| hits.iter().map(|hit| hit.field).collect::<Vec<_>>();
+
+        let hits = search(&items, &index, "ぴんと", SearchMode::AllFields);
+        assert_eq!(numbers(&hits), vec!["A00002", "A00003"]);
+        assert_eq!(
+            fields(&hits),
+            vec![Some(SearchField::FunctionalityToDisplay); 2]
+        );
+        assert!(search(&items, &index, "ぴんと", SearchMode::ProductName).is_empty());
+
+        let hits = search(&items, &index, "研究れびゅ", SearchMode::AllFields);
+        assert_eq!(numbers(&hits), vec!["A00001", "A00002", "A00003"]);
+        assert_eq!(
+            fields(&hits),
+            vec![Some(SearchField::GeneralReviewOfEvidence); 3]
+        );
+
+        let hits = search(&items, &index, "ａ００００２", SearchMode::AllFields);
+        assert_eq!(fields(&hits), vec![Some(SearchField::NotificationNumber)]);
+
+        let hits = search(&items, &index, "", SearchMode::AllFields);
+        assert_eq!(hits.len(), 3);
+        assert_eq!(fields(&hits), vec![None; 3]);
+    }
+
+    #[test]
+    fn returns_match_offsets_in_normalized_text() {
+        let normalized = normalize("ギャバ (ｷﾞｬﾊﾞ) 粒");
+        let matched = match_ranges(&normalized, &normalize("ぎゃば"))
+            .into_iter()
+            .map(|range| &normalized[range])
+            .collect::<Vec<&str>>();
+        assert_eq!(matched, vec!["ギヤバ", "ギヤバ"]);
+        assert!(match_ranges(&normalized, "").is_empty());
+
+        let items = vec![data(1, "ギャバ (ｷﾞｬﾊﾞ) 粒", "あんしん", "睡眠の質を高める")];
+        let index = SearchIndex::new(&items);
+        let hits = search(&items, &index, "ぎゃば", SearchMode::ProductName);
+        let text = index
+            .text(hits[0].position, SearchField::ProductName)
+            .unwrap();
+        let original = hits[0]
+            .matches
+            .iter()
+            .filter_map(|range| text.original_range(range.clone()))
+            .map(|range| &items[0].productName[range])
+            .collect::<Vec<&str>>();
+        assert_eq!(original, vec!["ギャバ", "ｷﾞｬﾊﾞ"]);
+    }
+
+    #[test]
+    fn single_character_and_missing_queries() {
+        let items = synthetic_items(100);
+        let index = SearchIndex::new(&items);
+        for query in ["ギ", "9", "ぎゃばx", "存在しない"] {
+            for mode in [SearchMode::ProductName, SearchMode::AllFields] {
+                assert_eq!(
+                    numbers(&search(&items, &index, query, mode)),
+                    numbers(&linear_search(&items, query, mode)),
+                    "query: {}",
+                    query
+                );
+            }
+        }
+    }
+
+    /// 1 万件の合成データで検索のレイテンシを計測する。
+    ///
+    /// 時間がかかるため通常のテストでは実行しない。
+    /// `cargo test --release benchmark_10k_items -- --ignored --nocapture` で計測結果を表示できる。
+    #[test]
+    #[ignore]
+    fn benchmark_10k_items() {
+        let items = synthetic_items(10_000);
+
+        let start = Instant::now();
+        let index = SearchIndex::new(&items);
+        println!("build index: {:?}", start.elapsed());
+
+        for query in ["ぎゃば", "dha", "血圧", "ブルベリ12", "ｺﾗｰｹﾞﾝ", "睡"] {
+            for mode in [SearchMode::ProductName, SearchMode::AllFields] {
+                let start = Instant::now();
+                let hits = search(&items, &index, query, mode);
+                let indexed = start.elapsed();
+
+                let start = Instant::now();
+                let expected = linear_search(&items, query, mode);
+                let linear = start.elapsed();
+
+                println!(
+                    "{:?} {:?}: {} hits, index {:?}, linear scan {:?}",
+                    mode,
+                    query,
+                    hits.len(),
+                    indexed,
+                    linear
+                );
+                assert_eq!(numbers(&hits), numbers(&expected));
+            }
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! 似た商品の推薦
+//!
+//! 表示しようとする機能性の文を文字 n-gram の TF-IDF ベクトルにし、コサイン類似度で似た商品を探す。
+//! 「評価の低い商品の代わりに何を買えばよいか」に答えられるよう、開いている商品より評価の高い
+//! 商品を先に並べる。
+
+use std::collections::HashMap;
+
+use crate::assessment::Grade;
+use crate::normalize::normalize;
+use crate::Data;
+
+/** n-gram の文字数 */
+const NGRAM: usize = 2;
+
+/** 表示する似た商品の最大数 */
+pub const MAX_RESULTS: usize = 5;
+
+/** 評価で並べ替える前に類似度の高い順に選ぶ候補の数 */
+const MAX_CANDIDATES: usize = 20;
+
+/** 似た商品とみなす類似度の下限 */
+const MIN_SIMILARITY: f32 = 0.2;
+
+/** 似た商品 */
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarProduct<'a> {
+    pub item: &'a Data,
+    /** コサイン類似度 (0.0..=1.0) */
+    pub score: f32,
+    /** 開いている商品より評価が高いかどうか。開いている商品の評価が不明の場合は常に `false` */
+    pub better: bool,
+}
+
+/** 商品ごとの TF-IDF ベクトル。データの取得時に一度だけ作る */
+#[derive(Debug, Default, PartialEq)]
+pub struct SimilarityIndex {
+    /** 届出番号から `vectors` の位置への対応 */
+    positions: HashMap<String, usize>,
+    /** n-gram の番号の順に並べた、長さを 1 にした TF-IDF ベクトル */
+    vectors: Vec<Vec<(u32, f32)>>,
+}
+
+/// 文字 n-gram を数える。句読点や空白をまたぐ n-gram は作らない。
+fn ngrams(text: &str) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    let normalized = normalize(text);
+    for segment in normalized.split(|c: char| !c.is_alphanumeric()) {
+        let chars = segment.chars().collect::<Vec<char>>();
+        for window in chars.windows(NGRAM) {
+            *counts.entry(window.iter().collect::<String>()).or_default() += 1;
+        }
+    }
+    counts
+}
+
+impl SimilarityIndex {
+    pub fn new(items: &[Data]) -> Self {
+        let documents = items
+            .iter()
+            .map(|item| ngrams(&item.functionalityToDisplay))
+            .collect::<Vec<_>>();
+
+        let mut terms: HashMap<&str, u32> = HashMap::new();
+        let mut document_frequencies: Vec<usize> = Vec::new();
+        for document in &documents {
+            for term in document.keys() {
+                let id = *terms.entry(term.as_str()).or_insert_with(|| {
+                    document_frequencies.push(0);
+                    (document_frequencies.len() - 1) as u32
+                });
+                document_frequencies[id as usize] += 1;
+            }
+        }
+
+        let count = documents.len() as f32;
+        let vectors = documents
+            .iter()
+            .map(|document| {
+                let mut vector = document
+                    .iter()
+                    .map(|(term, frequency)| {
+                        let id = terms[term.as_str()];
+                        // すべての商品に含まれる定型文の n-gram は重みを 0 にする
+                        let idf =
+                            ((count + 1.0) / (document_frequencies[id as usize] as f32 + 1.0)).ln();
+                        (id, (1.0 + (*frequency as f32).ln()) * idf)
+                    })
+                    .collect::<Vec<(u32, f32)>>();
+                vector.retain(|(_, w)| *w > 0.0);
+                let norm = vector.iter().map(|(_, w)| w * w).sum::<f32>().sqrt();
+                if norm > 0.0 {
+                    vector.iter_mut().for_each(|(_, w)| *w /= norm);
+                }
+                vector.sort_by_key(|(id, _)| *id);
+                vector
+            })
+            .collect();
+
+        SimilarityIndex {
+            positions: items
+                .iter()
+                .enumerate()
+                .map(|(position, item)| (item.notificationNumber.clone(), position))
+                .collect(),
+            vectors,
+        }
+    }
+
+    /// 2 つのベクトルのコサイン類似度。どちらも長さを 1 にしてあるため内積を求めればよい。
+    fn cosine(a: &[(u32, f32)], b: &[(u32, f32)]) -> f32 {
+        let (mut i, mut j, mut dot) = (0, 0, 0.0);
+        while i < a.len() && j < b.len() {
+            match a[i].0.cmp(&b[j].0) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    dot += a[i].1 * b[j].1;
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        dot
+    }
+
+    /// 商品に似た商品を返す。
+    ///
+    /// 類似度の高い候補から、開いている商品より評価の高い商品を先に、それぞれ類似度の高い順に並べる。
+    /// `items` はインデックスを作ったときと同じものを渡す。
+    pub fn similar<'a>(
+        &self,
+        items: &'a [Data],
+        notification_number: &str,
+    ) -> Vec<SimilarProduct<'a>> {
+        let Some(&position) = self.positions.get(notification_number) else {
+            return Vec::new();
+        };
+        let (Some(target), Some(vector)) = (items.get(position), self.vectors.get(position)) else {
+            return Vec::new();
+        };
+        let target_grade = target.assessment.grade();
+
+        let mut candidates = self
+            .vectors
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != position)
+            .filter_map(|(other, other_vector)| {
+                let item = items.get(other)?;
+                let score = Self::cosine(vector, other_vector);
+                (score >= MIN_SIMILARITY && item.notificationNumber != target.notificationNumber)
+                    .then_some(SimilarProduct {
+                        item,
+                        score,
+                        better: target_grade != Grade::Other
+                            && item.assessment.grade() < target_grade,
+                    })
+            })
+            .collect::<Vec<SimilarProduct>>();
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        candidates.truncate(MAX_CANDIDATES);
+        candidates.sort_by(|a, b| b.better.cmp(&a.better).then(b.score.total_cmp(&a.score)));
+        candidates.truncate(MAX_RESULTS);
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assessment::Assessment;
+
+    fn data(number: &str, functionality: &str, assessment: Assessment) -> Data {
+        Data {
+            functionalityToDisplay: functionality.to_string(),
+            assessment,
+            ..Data::sample(number)
+        }
+    }
+
+    fn items() -> Vec<Data> {
+        vec![
+            data(
+                "A1",
+                "本品には GABA が含まれます。GABA には血圧が高めの方の血圧を下げる機能があります。",
+                Assessment::D,
+            ),
+            data(
+                "A2",
+                "本品には GABA が含まれます。GABA には血圧が高めの方の血圧を下げる機能があります。",
+                Assessment::D,
+            ),
+            data(
+                "A3",
+                "本品には GABA が含まれます。血圧が高めの方に適した機能があります。",
+                Assessment::A,
+            ),
+            data(
+                "A4",
+                "本品にはルテインが含まれます。ルテインには目の調子を整える機能があります。",
+                Assessment::A,
+            ),
+            data(
+                "A5",
+                "本品にはグリシンが含まれます。グリシンには睡眠の質を高める機能があります。",
+                Assessment::B,
+            ),
+            data(
+                "A6",
+                "本品には茶カテキンが含まれます。茶カテキンには体脂肪を減らす機能があります。",
+                Assessment::C,
+            ),
+            data(
+                "A7",
+                "本品にはコラーゲンペプチドが含まれます。肌の潤いを保つ機能があります。",
+                Assessment::B,
+            ),
+        ]
+    }
+
+    fn numbers(similar: &[SimilarProduct]) -> Vec<String> {
+        similar
+            .iter()
+            .map(|s| s.item.notificationNumber.clone())
+            .collect()
+    }
+
+    #[test]
+    fn scores_identical_texts_highest() {
+        let items = items();
+        let index = SimilarityIndex::new(&items);
+        let a2 = SimilarityIndex::cosine(&index.vectors[0], &index.vectors[1]);
+        let a3 = SimilarityIndex::cosine(&index.vectors[0], &index.vectors[2]);
+        let a4 = SimilarityIndex::cosine(&index.vectors[0], &index.vectors[3]);
+        assert!((a2 - 1.0).abs() < 1e-5);
+        assert!(a2 > a3 && a3 > a4);
+    }
+
+    #[test]
+    fn ranks_better_graded_products_first() {
+        let items = items();
+        let index = SimilarityIndex::new(&items);
+        let similar = index.similar(&items, "A1");
+        assert_eq!(numbers(&similar), vec!["A3", "A2"]);
+        assert!(similar[0].better);
+        assert!(!similar[1].better);
+    }
+
+    #[test]
+    fn excludes_unrelated_and_unknown_products() {
+        let items = items();
+        let index = SimilarityIndex::new(&items);
+        assert!(index.similar(&items, "A4").is_empty());
+        assert!(index.similar(&items, "Z9").is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+//! データのスナップショットの圧縮形式
+//!
+//! JSON ではすべてのレコードに長いキーが繰り返し現れ、届出者名や評価などの同じ文字列も多い。
+//! キーと文字列を文字列表にまとめて番号で参照し、同じキーを持つオブジェクトの配列は列ごとに並べ替えて、
+//! postcard でバイナリにしてから deflate で圧縮する。
+//! JSON の値の構造はそのまま保つので、復元した値は JSON のレスポンスと同じように解釈できる。
+//!
+//! 形式: `MAGIC` に続けて deflate で圧縮した postcard の `Snapshot`
+//!
+//! アプリは読み込みだけを行い、変換は `examples/snapshot.rs` の変換ツールで行う。
+//! 変換ツールからも形式の定義を使うため、このモジュールはクレート内の他のモジュールに依存しない。
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+
+/** 形式を識別する先頭のバイト列。形式を変えた場合は数字を上げる */
+pub const MAGIC: &[u8; 4] = b"AMS1";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    /** キーと文字列の表 */
+    pub strings: Vec<String>,
+    pub root: CompactValue,
+}
+
+/** 文字列を文字列表の番号に置き換えた JSON の値 */
+#[derive(Debug, Serialize, Deserialize)]
+pub enum CompactValue {
+    Null,
+    Bool(bool),
+    /** 精度を落とさないよう、数値は表記を文字列表に入れる */
+    Number(u32),
+    String(u32),
+    Array(Vec<CompactValue>),
+    Object(Vec<(u32, CompactValue)>),
+    /** すべて同じキーを持つオブジェクトの配列。似た値が並ぶよう列ごとにまとめる */
+    Table {
+        keys: Vec<u32>,
+        columns: Vec<Vec<CompactValue>>,
+        rows: u32,
+    },
+}
+
+fn string(strings: &[String], index: u32) -> Result<&str> {
+    strings
+        .get(index as usize)
+        .map(String::as_str)
+        .ok_or_else(|| anyhow!("String index {} is out of range", index))
+}
+
+fn expand(value: CompactValue, strings: &[String]) -> Result<Value> {
+    Ok(match value {
+        CompactValue::Null => Value::Null,
+        CompactValue::Bool(value) => Value::Bool(value),
+        CompactValue::Number(index) => {
+            let number = string(strings, index)?;
+            Value::Number(
+                number
+                    .parse::<Number>()
+                    .with_context(|| format!("Invalid number: {:?}", number))?,
+            )
+        }
+        CompactValue::String(index) => Value::String(string(strings, index)?.to_string()),
+        CompactValue::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(|value| expand(value, strings))
+                .collect::<Result<Vec<Value>>>()?,
+        ),
+        CompactValue::Object(entries) => Value::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| {
+                    Ok((string(strings, key)?.to_string(), expand(value, strings)?))
+                })
+                .collect::<Result<Map<String, Value>>>()?,
+        ),
+        CompactValue::Table {
+            keys,
+            columns,
+            rows,
+        } => {
+            if keys.len() != columns.len()
+                || columns.iter().any(|column| column.len() != rows as usize)
+            {
+                return Err(anyhow!("Table columns do not match its keys and rows"));
+            }
+            let keys = keys
+                .into_iter()
+                .map(|key| string(strings, key))
+                .collect::<Result<Vec<&str>>>()?;
+            let mut maps = vec![Map::new(); rows as usize];
+            for (key, column) in keys.into_iter().zip(columns) {
+                for (map, value) in maps.iter_mut().zip(column) {
+                    map.insert(key.to_string(), expand(value, strings)?);
+                }
+            }
+            Value::Array(maps.into_iter().map(Value::Object).collect())
+        }
+    })
+}
+
+/// スナップショットを JSON の値に戻す。
+pub fn decode(bytes: &[u8]) -> Result<Value> {
+    let compressed = bytes
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| anyhow!("Not a snapshot (expected {:?})", MAGIC))?;
+    let serialized = miniz_oxide::inflate::decompress_to_vec(compressed)
+        .map_err(|err| anyhow!("Failed to decompress snapshot: {:?}", err.status))?;
+    let snapshot: Snapshot =
+        postcard::from_bytes(&serialized).context("Failed to deserialize snapshot")?;
+    expand(snapshot.root, &snapshot.strings)
+}
@@ -0,0 +1,254 @@
+//! データ全体の統計
+//!
+//! 「統計」画面のグラフに使う集計。読み込んだ `Vec<Data>` から画面を開くたびに作る。
+//! 健康の悩みの分類は、データの取得時に作った `ConcernIndex` を使う。
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::assessment::Grade;
+use crate::concern::{self, Concern, ConcernIndex};
+use crate::normalize::normalize;
+use crate::notifier;
+use crate::Data;
+
+/** 商品数の多い届出者として表示する件数 */
+pub const TOP_NOTIFIERS: usize = 10;
+
+/// グラフで評価の区分を塗る色。Bulma の tag の色に合わせる。
+pub fn grade_color(grade: Grade) -> &'static str {
+    match grade {
+        Grade::A => "#48c78e",
+        Grade::B => "#3e8ed0",
+        Grade::C => "#ffe08a",
+        Grade::D => "#f59aae",
+        Grade::E => "#f14668",
+        Grade::Other => "#dbdbdb",
+    }
+}
+
+/** 届出者ごとの商品数 */
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotifierCount {
+    /** `notifier::notifier_key` で作った会社のキー */
+    pub key: String,
+    pub name: String,
+    pub count: usize,
+}
+
+/** 健康の悩みごとの評価の区分の件数 */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConcernGrades {
+    pub concern: &'static Concern,
+    /** `Grade::ALL` の順の件数 */
+    pub grades: Vec<(Grade, usize)>,
+    pub total: usize,
+}
+
+/** 届出番号の系列ごとの件数 */
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeriesCount {
+    /** 届出番号の先頭の英字 (`A`, `B`, ...) */
+    pub series: String,
+    pub count: usize,
+    /** この系列までの累計 */
+    pub cumulative: usize,
+}
+
+/** 統計画面に表示する集計 */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statistics {
+    pub total: usize,
+    pub grades: Vec<(Grade, usize)>,
+    pub topNotifiers: Vec<NotifierCount>,
+    /** 商品のある悩みだけを辞書の順に並べる */
+    pub concernGrades: Vec<ConcernGrades>,
+    pub series: Vec<SeriesCount>,
+    /** 届出番号から系列を読み取れなかった商品の数 */
+    pub unknownSeries: usize,
+}
+
+impl Statistics {
+    pub fn new(items: &[Data], concern_index: &ConcernIndex) -> Self {
+        let (series, unknown_series) = series_counts(items);
+        Statistics {
+            total: items.len(),
+            grades: grade_counts(items.iter()),
+            topNotifiers: top_notifiers(items, TOP_NOTIFIERS),
+            concernGrades: concern_grades(items, concern::dictionary(), concern_index),
+            series,
+            unknownSeries: unknown_series,
+        }
+    }
+}
+
+/// 評価の区分ごとの件数を `Grade::ALL` の順に返す。
+fn grade_counts<'a>(items: impl Iterator<Item = &'a Data>) -> Vec<(Grade, usize)> {
+    let mut counts = Grade::ALL.map(|grade| (grade, 0));
+    for item in items {
+        let grade = item.assessment.grade();
+        if let Some((_, count)) = counts.iter_mut().find(|(g, _)| *g == grade) {
+            *count += 1;
+        }
+    }
+    counts.to_vec()
+}
+
+/// 商品数の多い届出者。同数の場合は表示名の順に並べる。
+fn top_notifiers(items: &[Data], limit: usize) -> Vec<NotifierCount> {
+    let mut groups: HashMap<String, Vec<&Data>> = HashMap::new();
+    for item in items {
+        groups
+            .entry(notifier::notifier_key(&item.notifierName))
+            .or_default()
+            .push(item);
+    }
+    let mut counts = groups
+        .into_iter()
+        .filter(|(key, _)| !key.is_empty())
+        .map(|(key, products)| NotifierCount {
+            name: notifier::display_name(&products).unwrap_or_else(|| key.clone()),
+            key,
+            count: products.len(),
+        })
+        .collect::<Vec<NotifierCount>>();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    counts.truncate(limit);
+    counts
+}
+
+fn concern_grades(
+    items: &[Data],
+    concerns: &'static [Concern],
+    concern_index: &ConcernIndex,
+) -> Vec<ConcernGrades> {
+    concerns
+        .iter()
+        .map(|concern| {
+            let products = items
+                .iter()
+                .filter(|item| concern_index.matches(&item.notificationNumber, Some(concern)))
+                .collect::<Vec<&Data>>();
+            ConcernGrades {
+                concern,
+                total: products.len(),
+                grades: grade_counts(products.into_iter()),
+            }
+        })
+        .filter(|concern_grades| concern_grades.total > 0)
+        .collect()
+}
+
+/// 届出番号の系列。`A123` の `A` のような先頭の英字を大文字で返す。
+pub fn series_of(notification_number: &str) -> Option<String> {
+    let series = normalize(notification_number.trim())
+        .chars()
+        .take_while(char::is_ascii_alphabetic)
+        .collect::<String>()
+        .to_uppercase();
+    (!series.is_empty()).then_some(series)
+}
+
+/// 系列ごとの件数と累計。系列は `A`, `B`, ..., `Z`, `AA` の順に並べる。
+fn series_counts(items: &[Data]) -> (Vec<SeriesCount>, usize) {
+    let mut counts: BTreeMap<(usize, String), usize> = BTreeMap::new();
+    let mut unknown = 0;
+    for item in items {
+        match series_of(&item.notificationNumber) {
+            Some(series) => *counts.entry((series.len(), series)).or_default() += 1,
+            None => unknown += 1,
+        }
+    }
+    let mut cumulative = 0;
+    let series = counts
+        .into_iter()
+        .map(|((_, series), count)| {
+            cumulative += count;
+            SeriesCount {
+                series,
+                count,
+                cumulative,
+            }
+        })
+        .collect();
+    (series, unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assessment::Assessment;
+
+    fn data(
+        number: &str,
+        notifier_name: &str,
+        functionality: &str,
+        assessment: Assessment,
+    ) -> Data {
+        Data {
+            notifierName: notifier_name.to_string(),
+            functionalityToDisplay: functionality.to_string(),
+            assessment,
+            ..Data::sample(number)
+        }
+    }
+
+    fn items() -> Vec<Data> {
+        vec![
+            data("A1", "株式会社サンプル", "血圧を下げる", Assessment::A),
+            data("A2", "(株)サンプル", "睡眠の質を高める", Assessment::C),
+            data("B1", "サンプル食品", "血圧を下げる", Assessment::C),
+            data("AA1", "サンプル株式会社", "肌の潤い", Assessment::E),
+            data("ｂ2", "サンプル食品", "目の調子", Assessment::A),
+            data("123", "", "不明", Assessment::Unknown("".to_string())),
+        ]
+    }
+
+    #[test]
+    fn counts_grades() {
+        let stats = Statistics::new(&items(), &ConcernIndex::new(&items()));
+        assert_eq!(stats.total, 6);
+        assert_eq!(stats.grades[0], (Grade::A, 2));
+        assert_eq!(stats.grades[2], (Grade::C, 2));
+        assert_eq!(stats.grades[5], (Grade::Other, 1));
+    }
+
+    #[test]
+    fn ranks_notifiers_by_normalized_name() {
+        let notifiers = top_notifiers(&items(), 1);
+        assert_eq!(notifiers.len(), 1);
+        assert_eq!(notifiers[0].count, 3);
+        assert_eq!(notifiers[0].key, notifier::notifier_key("サンプル"));
+        assert_eq!(top_notifiers(&items(), 10).len(), 2);
+    }
+
+    #[test]
+    fn counts_grades_per_concern() {
+        let stats = Statistics::new(&items(), &ConcernIndex::new(&items()));
+        let blood_pressure = stats
+            .concernGrades
+            .iter()
+            .find(|concern_grades| concern_grades.concern.id == "blood-pressure")
+            .unwrap();
+        assert_eq!(blood_pressure.total, 2);
+        assert_eq!(blood_pressure.grades[0], (Grade::A, 1));
+        assert_eq!(blood_pressure.grades[2], (Grade::C, 1));
+        assert!(stats
+            .concernGrades
+            .iter()
+            .all(|concern_grades| concern_grades.total > 0));
+    }
+
+    #[test]
+    fn counts_notification_number_series() {
+        assert_eq!(series_of(" ａ123 ").as_deref(), Some("A"));
+        assert_eq!(series_of("123"), None);
+        let stats = Statistics::new(&items(), &ConcernIndex::new(&items()));
+        let series = stats
+            .series
+            .iter()
+            .map(|s| (s.series.as_str(), s.count, s.cumulative))
+            .collect::<Vec<_>>();
+        assert_eq!(series, vec![("A", 2, 2), ("B", 2, 4), ("AA", 1, 5)]);
+        assert_eq!(stats.unknownSeries, 1);
+    }
+}
@@ -0,0 +1,56 @@
+//! ブラウザの localStorage への保存
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::env;
+
+/// 最後に取得に成功したデータのキー。
+///
+/// API のバージョンが変わった場合は古いキャッシュを読まないようキーに含める。
+pub fn data_cache_key() -> String {
+    format!("anshin-meshi:data:v{}", env::APP_API_VERSION)
+}
+
+/** 前回までの訪問で見たデータの指紋のキー */
+pub const VISIT_HISTORY_KEY: &str = "anshin-meshi:visits";
+
+/** お気に入りのキー */
+pub const FAVORITES_KEY: &str = "anshin-meshi:favorites";
+
+/** 最近見た商品のキー */
+pub const RECENTLY_VIEWED_KEY: &str = "anshin-meshi:recently-viewed";
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+/// 保存した値を読み込む。値がない場合や読み込めない場合は `None` を返す。
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let value = local_storage()?.get_item(key).ok().flatten()?;
+    match serde_json::from_str(&value) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            log::warn!("Failed to parse local storage {}: {:?}", key, err);
+            None
+        }
+    }
+}
+
+/// 値を保存する。容量不足などで保存できなかった場合はログに出力して無視する。
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    let Some(storage) = local_storage() else {
+        log::warn!("Local storage is not available");
+        return;
+    };
+    let result = serde_json::to_string(value)
+        .map_err(|err| format!("{:?}", err))
+        .and_then(|value| {
+            storage
+                .set_item(key, &value)
+                .map_err(|err| format!("{:?}", err))
+        });
+    if let Err(err) = result {
+        log::warn!("Failed to save local storage {}: {}", key, err);
+    }
+}